@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let expected = fjson::scanner::Scanner::new(input).collect::<Vec<_>>();
+        let output = fjson::byte_scanner::ByteScanner::new(input).collect::<Vec<_>>();
+        assert_eq!(output, expected);
+    }
+});
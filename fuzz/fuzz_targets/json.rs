@@ -4,19 +4,15 @@ use libfuzzer_sys::fuzz_target;
 
 fuzz_target!(|data: &[u8]| {
     if let Ok(s) = std::str::from_utf8(data) {
-        if let Ok(out) = fjson::to_json_compact(s) {
-            match serde_json::from_str::<serde_json::Value>(&out) {
-                Ok(_) => {}
-                Err(err) => {
-                    let s = err.to_string();
-                    if s.contains("number out of range")
-                        || s.contains("lone leading surrogate in hex escape")
-                        || s.contains("unexpected end of hex escape")
-                    {
-                        return;
-                    }
-                    panic!("{}", err);
-                }
+        let mut out = String::new();
+        let scanner = fjson::byte_scanner::ByteScanner::new(s).with_finite_numbers(true);
+        let opts = fjson::format::Options::default()
+            .with_lone_surrogates(fjson::format::LoneSurrogates::Replace);
+        if fjson::format::write_json_compact_iter_opts(&mut out, scanner.without_metadata(), &opts)
+            .is_ok()
+        {
+            if let Err(err) = serde_json::from_str::<serde_json::Value>(&out) {
+                panic!("{}", err);
             }
         } else {
             if let Ok(_) = serde_json::from_slice::<serde_json::Value>(data) {
@@ -1,9 +1,12 @@
 //! Parse JSONC into an abstract syntax tree.
 
-use std::iter::Peekable;
+use std::{borrow::Cow, cell::Cell, collections::HashMap, iter::Peekable};
 
+use crate::encoding::decode;
 use crate::error::Error;
-use crate::scanner::{Event, ScanResult, Scanner, Token};
+use crate::escape::unescape;
+use crate::number::Number;
+use crate::scanner::{Event, LineEnding, LineEndings, ScanResult, Scanner, Token};
 
 /// Root represents the root JSON value. It may include `Metadata` above and
 /// below the actual value.
@@ -12,14 +15,69 @@ pub struct Root<'a> {
     pub meta_above: Vec<Metadata<'a>>,
     pub value: Value<'a>,
     pub meta_below: Vec<Metadata<'a>>,
+    pub(crate) line_ending: LineEnding,
+}
+
+impl<'a> Root<'a> {
+    /// Returns a new Root with `\n` line endings.
+    pub fn new(
+        meta_above: Vec<Metadata<'a>>,
+        value: Value<'a>,
+        meta_below: Vec<Metadata<'a>>,
+    ) -> Self {
+        Self {
+            meta_above,
+            value,
+            meta_below,
+            line_ending: LineEnding::Lf,
+        }
+    }
+
+    /// Returns the line ending used most often in the source.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
 }
 
 /// Value represents a JSON value. The `comments` field includes any comments
 /// located on the same line as the value.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Value<'a> {
     pub token: ValueToken<'a>,
     pub comments: Vec<Comment<'a>>,
+    pub(crate) expanded: bool,
+}
+
+impl<'a> Value<'a> {
+    /// Returns a new Value that wasn't expanded in the source.
+    pub fn new(token: ValueToken<'a>, comments: Vec<Comment<'a>>) -> Self {
+        Self {
+            token,
+            comments,
+            expanded: false,
+        }
+    }
+
+    /// Returns true if the value is an object or array that was expanded in
+    /// the source, by a newline after its opening bracket or a trailing comma.
+    /// See [Options::with_preserve_expanded](crate::format::Options::with_preserve_expanded).
+    pub fn expanded(&self) -> bool {
+        self.expanded
+    }
+
+    /// Replaces the token with the token of `value`, keeping the comments.
+    pub(crate) fn replace_token(&mut self, value: Value<'a>) {
+        self.token = value.token;
+        self.expanded = value.expanded;
+    }
+}
+
+/// Whether a value was expanded is only a hint for formatting, so it isn't
+/// compared.
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token && self.comments == other.comments
+    }
 }
 
 /// ValueToken represents the JSON "token" of a `Value`.
@@ -64,24 +122,57 @@ pub enum Comment<'a> {
 const RECURSION_LIMIT: u8 = 128;
 
 /// Parse the provided JSON string into a `Root` object.
-pub fn parse(input: &str) -> Result<Root, Error> {
+pub fn parse(input: &str) -> Result<Root<'_>, Error> {
     parse_iter(Scanner::new(input))
 }
 
+/// Parse the provided JSON bytes into a `Root` object.
+///
+/// The input is decoded with [decode](crate::encoding::decode). If the input
+/// must be transcoded (i.e. it is UTF-16), the decoded string is stored in
+/// `buf` and the returned `Root` borrows from it.
+pub fn parse_bytes<'a>(input: &'a [u8], buf: &'a mut String) -> Result<Root<'a>, Error> {
+    match decode(input)? {
+        Cow::Borrowed(s) => parse(s),
+        Cow::Owned(s) => {
+            *buf = s;
+            parse(buf)
+        }
+    }
+}
+
 /// Parse the provided `Iterator` of `ScanResult`s into a `Root` object. The
 /// iterator should be created via a `Scanner` instance.
 pub fn parse_iter<'a, I>(iter: I) -> Result<Root<'a>, Error>
 where
     I: Iterator<Item = ScanResult<'a>>,
 {
-    let mut s = iter.peekable();
+    let line_endings = Cell::new(LineEndings::default());
+    let mut s = iter
+        .filter(|result| {
+            !matches!(
+                result,
+                Ok(Event {
+                    token: Token::Whitespace(_),
+                    ..
+                })
+            )
+        })
+        .inspect(|result| {
+            if let Ok(event) = result {
+                let mut counts = line_endings.get();
+                counts.add_event(event);
+                line_endings.set(counts);
+            }
+        })
+        .peekable();
     parse_newlines(&mut s)?;
     let mut meta_above = Vec::new();
     while let Some(meta) = parse_metadata(&mut s)? {
         meta_above.push(meta);
     }
-    let typ = parse_next_value(&mut s, RECURSION_LIMIT)?;
-    let comments = parse_sameline_comments(&mut s)?;
+    let mut value = parse_next_value(&mut s, RECURSION_LIMIT)?;
+    value.comments = parse_sameline_comments(&mut s)?;
     let mut meta_below = Vec::new();
     while let Some(meta) = parse_metadata(&mut s)? {
         meta_below.push(meta);
@@ -94,18 +185,27 @@ where
     }
     Ok(Root {
         meta_above,
-        value: Value {
-            token: typ,
-            comments,
-        },
+        value,
         meta_below,
+        line_ending: line_endings.get().dominant(),
     })
 }
 
-fn parse_next_value<'a, I>(
-    s: &mut Peekable<I>,
-    remaining_depth: u8,
-) -> Result<ValueToken<'a>, Error>
+/// Parse a single JSON value that spans the entire `Iterator`, as if it was
+/// nested in `depth` containers. The returned value has no comments.
+pub(crate) fn parse_nested<'a, I>(iter: I, depth: u8) -> Result<Value<'a>, Error>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    let mut s = iter.peekable();
+    let value = parse_next_value(&mut s, RECURSION_LIMIT - depth)?;
+    if let Some(event) = next_event(&mut s)? {
+        return Err(event.into());
+    }
+    Ok(value)
+}
+
+fn parse_next_value<'a, I>(s: &mut Peekable<I>, remaining_depth: u8) -> Result<Value<'a>, Error>
 where
     I: Iterator<Item = ScanResult<'a>>,
 {
@@ -120,11 +220,11 @@ fn parse_value<'a, I>(
     s: &mut Peekable<I>,
     event: Event<'a>,
     remaining_depth: u8,
-) -> Result<ValueToken<'a>, Error>
+) -> Result<Value<'a>, Error>
 where
     I: Iterator<Item = ScanResult<'a>>,
 {
-    let typ = match event.token {
+    let (token, expanded) = match event.token {
         Token::ObjectStart => {
             let remaining_depth = remaining_depth - 1;
             if remaining_depth == 0 {
@@ -139,20 +239,29 @@ where
             }
             parse_array(s, remaining_depth)?
         }
-        Token::Null => ValueToken::Null,
-        Token::String(v) => ValueToken::String(v),
-        Token::Number(v) => ValueToken::Number(v),
-        Token::Bool(v) => ValueToken::Bool(v),
+        Token::Null => (ValueToken::Null, false),
+        Token::String(v) => (ValueToken::String(v), false),
+        Token::Number(v) => (ValueToken::Number(v), false),
+        Token::Bool(v) => (ValueToken::Bool(v), false),
         _ => return Err(event.into()),
     };
-    Ok(typ)
+    Ok(Value {
+        token,
+        comments: Vec::new(),
+        expanded,
+    })
 }
 
-fn parse_object<'a, I>(s: &mut Peekable<I>, remaining_depth: u8) -> Result<ValueToken<'a>, Error>
+/// Parses the members of an object, returning whether it has expansion
+/// hints: a newline after the opening bracket or a trailing comma.
+fn parse_object<'a, I>(
+    s: &mut Peekable<I>,
+    remaining_depth: u8,
+) -> Result<(ValueToken<'a>, bool), Error>
 where
     I: Iterator<Item = ScanResult<'a>>,
 {
-    skip_newlines(s)?;
+    let mut expanded = skip_newlines(s)? > 0;
 
     let mut vals = Vec::new();
     loop {
@@ -165,7 +274,11 @@ where
             None => return Err(Error::UnexpectedEOF),
         };
         match event.token {
-            Token::ObjectEnd => break,
+            Token::ObjectEnd => {
+                // Only reached directly after a comma or the opening bracket.
+                expanded |= vals.iter().any(|v| matches!(v, ObjectValue::KeyVal(..)));
+                break;
+            }
             Token::String(key) => {
                 skip_newlines(s)?;
                 while let Some(meta) = parse_metadata(s)? {
@@ -186,7 +299,7 @@ where
                     vals.push(ObjectValue::Metadata(meta));
                 }
 
-                let typ = parse_next_value(s, remaining_depth)?;
+                let mut value = parse_next_value(s, remaining_depth)?;
                 let mut comments = Vec::new();
 
                 let mut comma = false;
@@ -214,13 +327,8 @@ where
                     }
                 }
 
-                vals.push(ObjectValue::KeyVal(
-                    key,
-                    Value {
-                        token: typ,
-                        comments,
-                    },
-                ));
+                value.comments = comments;
+                vals.push(ObjectValue::KeyVal(key, value));
 
                 if !comma {
                     while let Some(meta) = parse_metadata(s)? {
@@ -244,14 +352,19 @@ where
         vals.pop();
     }
 
-    Ok(ValueToken::Object(vals))
+    Ok((ValueToken::Object(vals), expanded))
 }
 
-fn parse_array<'a, I>(s: &mut Peekable<I>, remaining_depth: u8) -> Result<ValueToken<'a>, Error>
+/// Parses the values of an array, returning whether it has expansion hints.
+/// See [parse_object].
+fn parse_array<'a, I>(
+    s: &mut Peekable<I>,
+    remaining_depth: u8,
+) -> Result<(ValueToken<'a>, bool), Error>
 where
     I: Iterator<Item = ScanResult<'a>>,
 {
-    skip_newlines(s)?;
+    let mut expanded = skip_newlines(s)? > 0;
 
     let mut vals = Vec::new();
     loop {
@@ -262,11 +375,12 @@ where
         if let Some(event) = peek_event(s)? {
             if event.token == Token::ArrayEnd {
                 skip_event(s)?;
+                expanded |= vals.iter().any(|v| matches!(v, ArrayValue::ArrayVal(_)));
                 break;
             }
         }
 
-        let typ = parse_next_value(s, remaining_depth)?;
+        let mut value = parse_next_value(s, remaining_depth)?;
         let mut comments = Vec::new();
 
         let mut comma = false;
@@ -294,10 +408,8 @@ where
             }
         }
 
-        vals.push(ArrayValue::ArrayVal(Value {
-            token: typ,
-            comments,
-        }));
+        value.comments = comments;
+        vals.push(ArrayValue::ArrayVal(value));
 
         if !comma {
             while let Some(meta) = parse_metadata(s)? {
@@ -318,7 +430,7 @@ where
         vals.pop();
     }
 
-    Ok(ValueToken::Array(vals))
+    Ok((ValueToken::Array(vals), expanded))
 }
 
 fn parse_newlines<'a, I>(s: &mut Peekable<I>) -> Result<usize, Error>
@@ -437,7 +549,7 @@ pub fn strip_metadata(root: &mut Root) {
     strip_meta_value(&mut root.value);
 }
 
-fn strip_meta_value(value: &mut Value) {
+pub(crate) fn strip_meta_value(value: &mut Value) {
     value.comments.clear();
     match value.token {
         ValueToken::Object(ref mut vals) => vals.retain_mut(|v| match v {
@@ -458,6 +570,47 @@ fn strip_meta_value(value: &mut Value) {
     }
 }
 
+/// Returns true if the values are equal as JSON, ignoring comments.
+///
+/// Strings are compared by their unescaped contents, numbers by their decimal
+/// value, and objects without regard to the order of their members. If an
+/// object has duplicate keys, the last one is used.
+pub fn json_eq(a: &Value, b: &Value) -> bool {
+    match (&a.token, &b.token) {
+        (ValueToken::Object(a), ValueToken::Object(b)) => {
+            let (a, b) = (object_members(a), object_members(b));
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, v)| b.get(k).is_some_and(|other| json_eq(v, other)))
+        }
+        (ValueToken::Array(a), ValueToken::Array(b)) => {
+            let (a, b) = (array_values(a), array_values(b));
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (ValueToken::String(a), ValueToken::String(b)) => unescape(a) == unescape(b),
+        (ValueToken::Number(a), ValueToken::Number(b)) => Number::new(a).value_eq(&Number::new(b)),
+        (a, b) => a == b,
+    }
+}
+
+fn array_values<'v, 'a>(vals: &'v [ArrayValue<'a>]) -> Vec<&'v Value<'a>> {
+    vals.iter()
+        .filter_map(|v| match v {
+            ArrayValue::ArrayVal(v) => Some(v),
+            ArrayValue::Metadata(_) => None,
+        })
+        .collect()
+}
+
+fn object_members<'v, 'a>(vals: &'v [ObjectValue<'a>]) -> HashMap<Cow<'a, str>, &'v Value<'a>> {
+    vals.iter()
+        .filter_map(|v| match v {
+            ObjectValue::KeyVal(k, v) => Some((unescape(k), v)),
+            ObjectValue::Metadata(_) => None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -511,6 +664,7 @@ mod tests {
                         Value {
                             token: ValueToken::String("val1"),
                             comments: vec![Comment::Line(" Same line comment.")],
+                            expanded: false,
                         },
                     ),
                     ObjectValue::KeyVal(
@@ -518,6 +672,7 @@ mod tests {
                         Value {
                             token: ValueToken::String("v"),
                             comments: vec![],
+                            expanded: false,
                         },
                     ),
                     ObjectValue::Metadata(Metadata::Comment(Comment::Line(" Next line comment."))),
@@ -532,19 +687,23 @@ mod tests {
                                 ArrayValue::ArrayVal(Value {
                                     token: ValueToken::String("val1"),
                                     comments: vec![],
+                                    expanded: false,
                                 }),
                                 ArrayValue::ArrayVal(Value {
                                     token: ValueToken::Number("100"),
                                     comments: vec![Comment::Line(" Before comma")],
+                                    expanded: false,
                                 }),
                                 ArrayValue::Metadata(Metadata::Newline),
                                 ArrayValue::Metadata(Metadata::Comment(Comment::Line(" True."))),
                                 ArrayValue::ArrayVal(Value {
                                     token: ValueToken::Bool(true),
                                     comments: vec![],
+                                    expanded: false,
                                 }),
                             ]),
                             comments: vec![],
+                            expanded: true,
                         },
                     ),
                     ObjectValue::Metadata(Metadata::Newline),
@@ -561,6 +720,7 @@ mod tests {
                                     Value {
                                         token: ValueToken::Number("100"),
                                         comments: vec![],
+                                        expanded: false,
                                     },
                                 ),
                                 ObjectValue::KeyVal(
@@ -568,6 +728,7 @@ mod tests {
                                     Value {
                                         token: ValueToken::Bool(true),
                                         comments: vec![],
+                                        expanded: false,
                                     },
                                 ),
                                 ObjectValue::KeyVal(
@@ -575,6 +736,7 @@ mod tests {
                                     Value {
                                         token: ValueToken::String("this"),
                                         comments: vec![],
+                                        expanded: false,
                                     },
                                 ),
                                 ObjectValue::Metadata(Metadata::Newline),
@@ -586,6 +748,7 @@ mod tests {
                                     Value {
                                         token: ValueToken::String("a"),
                                         comments: vec![],
+                                        expanded: false,
                                     },
                                 ),
                                 ObjectValue::KeyVal(
@@ -596,22 +759,59 @@ mod tests {
                                             Value {
                                                 token: ValueToken::String("object"),
                                                 comments: vec![],
+                                                expanded: false,
                                             },
                                         )]),
                                         comments: vec![],
+                                        expanded: true,
                                     },
                                 ),
                             ]),
                             comments: vec![],
+                            expanded: true,
                         },
                     ),
                 ]),
                 comments: vec![Comment::Line(" Trailing comment.")],
+                expanded: true,
             },
             meta_below: vec![],
+            line_ending: LineEnding::Lf,
         };
 
         let root = parse(input).expect("unexpected parsing error");
         assert_eq!(root, expected);
     }
+
+    #[test]
+    fn test_parse_bytes() {
+        let input: Vec<u8> = "\u{feff}[\"é\"] // Comment."
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        let mut buf = String::new();
+        let root = parse_bytes(&input, &mut buf).expect("unexpected parsing error");
+        assert_eq!(root, parse("[\"é\"] // Comment.").unwrap());
+    }
+
+    #[test]
+    fn test_parse_line_ending() {
+        let root = parse("{\r\n  \"a\": 1,\r\n  \"b\": 2\n}").unwrap();
+        assert_eq!(root.line_ending(), LineEnding::Crlf);
+        let lf_root = parse("{\n  \"a\": 1,\n  \"b\": 2\r\n}").unwrap();
+        assert_eq!(lf_root.line_ending(), LineEnding::Lf);
+        assert_eq!(root.value, lf_root.value);
+
+        let root = parse("/*\n\n*/ [\r\n1\r\n]").unwrap();
+        assert_eq!(root.line_ending(), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_parse_expanded() {
+        let expanded = parse("[\n1, {\"a\": 2,}]").unwrap();
+        let compact = parse("[1, {\"a\": 2}]").unwrap();
+        assert!(expanded.value.expanded());
+        assert!(!compact.value.expanded());
+        assert_eq!(expanded, compact);
+    }
 }
@@ -0,0 +1,332 @@
+//! A model of objects and arrays where each member owns its comments.
+//!
+//! In the AST, comments above a member are siblings of the member. Converting
+//! the members of an object or array into an [Object] or [Array] attaches
+//! those comments to the member they document, so that moving or removing a
+//! member keeps its documentation with it. Converting back produces the same
+//! AST.
+
+use crate::{
+    ast::{ArrayValue, Comment, Metadata, ObjectValue, Value},
+    escape::unescape,
+};
+
+/// Member is an object member along with the metadata above it.
+///
+/// The `comments` are the lines directly above the key. Any metadata that is
+/// separated from the key by a blank line, such as a comment describing a
+/// group of members, is kept in `detached`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member<'a> {
+    pub detached: Vec<Metadata<'a>>,
+    pub comments: Vec<Comment<'a>>,
+    pub key: &'a str,
+    pub value: Value<'a>,
+}
+
+/// Element is an array value along with the metadata above it. See [Member].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element<'a> {
+    pub detached: Vec<Metadata<'a>>,
+    pub comments: Vec<Comment<'a>>,
+    pub value: Value<'a>,
+}
+
+/// Object is the members of an object, with any metadata after the last
+/// member kept in `dangling`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Object<'a> {
+    pub members: Vec<Member<'a>>,
+    pub dangling: Vec<Metadata<'a>>,
+}
+
+/// Array is the elements of an array, with any metadata after the last
+/// element kept in `dangling`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Array<'a> {
+    pub elements: Vec<Element<'a>>,
+    pub dangling: Vec<Metadata<'a>>,
+}
+
+impl<'a> Member<'a> {
+    /// Creates a new Member without any comments.
+    pub fn new(key: &'a str, value: Value<'a>) -> Self {
+        Self {
+            detached: Vec::new(),
+            comments: Vec::new(),
+            key,
+            value,
+        }
+    }
+
+    /// Returns true if a blank line separates the member from what precedes
+    /// it.
+    pub fn starts_group(&self) -> bool {
+        self.detached.contains(&Metadata::Newline)
+    }
+}
+
+impl<'a> Element<'a> {
+    /// Creates a new Element without any comments.
+    pub fn new(value: Value<'a>) -> Self {
+        Self {
+            detached: Vec::new(),
+            comments: Vec::new(),
+            value,
+        }
+    }
+
+    /// Returns true if a blank line separates the element from what precedes
+    /// it.
+    pub fn starts_group(&self) -> bool {
+        self.detached.contains(&Metadata::Newline)
+    }
+}
+
+impl<'a> Object<'a> {
+    /// Returns the member with the provided (unescaped) key. If there are
+    /// duplicate keys, the last one is returned.
+    pub fn get(&self, key: &str) -> Option<&Member<'a>> {
+        self.members.iter().rev().find(|m| unescape(m.key) == key)
+    }
+
+    /// Returns the member with the provided (unescaped) key mutably. If there
+    /// are duplicate keys, the last one is returned.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Member<'a>> {
+        self.members
+            .iter_mut()
+            .rev()
+            .find(|m| unescape(m.key) == key)
+    }
+
+    /// Adds a member to the end of the object, replacing any members with the
+    /// same key.
+    pub fn insert(&mut self, member: Member<'a>) {
+        let key = unescape(member.key).into_owned();
+        while self.remove(&key).is_some() {}
+        self.members.push(member);
+    }
+
+    /// Removes the member with the provided (unescaped) key along with its
+    /// comments. Its detached metadata is kept and moved to the next member.
+    pub fn remove(&mut self, key: &str) -> Option<Member<'a>> {
+        let i = self.members.iter().rposition(|m| unescape(m.key) == key)?;
+        let mut member = self.members.remove(i);
+        let detached = std::mem::take(&mut member.detached);
+        match self.members.get_mut(i) {
+            Some(next) => prepend(&mut next.detached, detached),
+            None => prepend(&mut self.dangling, detached),
+        }
+        Some(member)
+    }
+}
+
+impl<'a> Array<'a> {
+    /// Removes the element at `index` along with its comments. Its detached
+    /// metadata is kept and moved to the next element.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Element<'a> {
+        let mut element = self.elements.remove(index);
+        let detached = std::mem::take(&mut element.detached);
+        match self.elements.get_mut(index) {
+            Some(next) => prepend(&mut next.detached, detached),
+            None => prepend(&mut self.dangling, detached),
+        }
+        element
+    }
+}
+
+fn prepend<'a>(meta: &mut Vec<Metadata<'a>>, mut detached: Vec<Metadata<'a>>) {
+    detached.append(meta);
+    *meta = detached;
+}
+
+/// Splits the metadata above a member at its last blank line.
+fn split_metadata<'a>(mut meta: Vec<Metadata<'a>>) -> (Vec<Metadata<'a>>, Vec<Comment<'a>>) {
+    let i = meta
+        .iter()
+        .rposition(|m| *m == Metadata::Newline)
+        .map_or(0, |i| i + 1);
+    let comments = meta
+        .split_off(i)
+        .into_iter()
+        .filter_map(|m| match m {
+            Metadata::Comment(c) => Some(c),
+            Metadata::Newline => None,
+        })
+        .collect();
+    (meta, comments)
+}
+
+impl<'a> std::convert::From<Vec<ObjectValue<'a>>> for Object<'a> {
+    fn from(vals: Vec<ObjectValue<'a>>) -> Self {
+        let mut members = Vec::new();
+        let mut pending = Vec::new();
+        for val in vals {
+            match val {
+                ObjectValue::Metadata(meta) => pending.push(meta),
+                ObjectValue::KeyVal(key, value) => {
+                    let (detached, comments) = split_metadata(std::mem::take(&mut pending));
+                    members.push(Member {
+                        detached,
+                        comments,
+                        key,
+                        value,
+                    });
+                }
+            }
+        }
+        Object {
+            members,
+            dangling: pending,
+        }
+    }
+}
+
+impl<'a> std::convert::From<Object<'a>> for Vec<ObjectValue<'a>> {
+    fn from(object: Object<'a>) -> Self {
+        let mut vals = Vec::new();
+        for member in object.members {
+            vals.extend(member.detached.into_iter().map(ObjectValue::Metadata));
+            vals.extend(
+                member
+                    .comments
+                    .into_iter()
+                    .map(|c| ObjectValue::Metadata(Metadata::Comment(c))),
+            );
+            vals.push(ObjectValue::KeyVal(member.key, member.value));
+        }
+        vals.extend(object.dangling.into_iter().map(ObjectValue::Metadata));
+        vals
+    }
+}
+
+impl<'a> std::convert::From<Vec<ArrayValue<'a>>> for Array<'a> {
+    fn from(vals: Vec<ArrayValue<'a>>) -> Self {
+        let mut elements = Vec::new();
+        let mut pending = Vec::new();
+        for val in vals {
+            match val {
+                ArrayValue::Metadata(meta) => pending.push(meta),
+                ArrayValue::ArrayVal(value) => {
+                    let (detached, comments) = split_metadata(std::mem::take(&mut pending));
+                    elements.push(Element {
+                        detached,
+                        comments,
+                        value,
+                    });
+                }
+            }
+        }
+        Array {
+            elements,
+            dangling: pending,
+        }
+    }
+}
+
+impl<'a> std::convert::From<Array<'a>> for Vec<ArrayValue<'a>> {
+    fn from(array: Array<'a>) -> Self {
+        let mut vals = Vec::new();
+        for element in array.elements {
+            vals.extend(element.detached.into_iter().map(ArrayValue::Metadata));
+            vals.extend(
+                element
+                    .comments
+                    .into_iter()
+                    .map(|c| ArrayValue::Metadata(Metadata::Comment(c))),
+            );
+            vals.push(ArrayValue::ArrayVal(element.value));
+        }
+        vals.extend(array.dangling.into_iter().map(ArrayValue::Metadata));
+        vals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{parse, ValueToken};
+
+    const INPUT: &str = r#"{
+        // Group header.
+
+        // Documents a.
+        "a": 1, // Same line.
+        "b": 2,
+
+        // Documents c.
+        "c": [
+            // First.
+            1,
+            2,
+            // Dangling.
+        ],
+    }"#;
+
+    fn object_vals(input: &str) -> Vec<ObjectValue<'_>> {
+        match parse(input).unwrap().value.token {
+            ValueToken::Object(vals) => vals,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_attached() {
+        let vals = object_vals(INPUT);
+        let object = Object::from(vals.clone());
+        assert_eq!(object.members.len(), 3);
+        assert_eq!(
+            object.members[0].detached,
+            [
+                Metadata::Comment(Comment::Line(" Group header.")),
+                Metadata::Newline
+            ]
+        );
+        assert_eq!(object.members[0].comments, [Comment::Line(" Documents a.")]);
+        assert!(!object.members[1].starts_group());
+        assert!(object.members[2].starts_group());
+        assert_eq!(object.members[2].comments, [Comment::Line(" Documents c.")]);
+
+        let array = match &object.get("c").unwrap().value.token {
+            ValueToken::Array(vals) => Array::from(vals.clone()),
+            _ => unreachable!(),
+        };
+        assert_eq!(array.elements[0].comments, [Comment::Line(" First.")]);
+        assert_eq!(
+            array.dangling,
+            [Metadata::Comment(Comment::Line(" Dangling."))]
+        );
+
+        assert_eq!(Vec::<ObjectValue>::from(object), vals);
+    }
+
+    #[test]
+    fn test_attached_remove() {
+        let mut object = Object::from(object_vals(INPUT));
+        let removed = object.remove("a").unwrap();
+        assert_eq!(removed.comments, [Comment::Line(" Documents a.")]);
+        assert_eq!(removed.value.comments, [Comment::Line(" Same line.")]);
+        assert!(object.get("a").is_none());
+        object.insert(Member::new("d", Value::new(ValueToken::Null, vec![])));
+
+        let expected = object_vals(
+            r#"{
+            // Group header.
+
+            "b": 2,
+
+            // Documents c.
+            "c": [
+                // First.
+                1,
+                2,
+                // Dangling.
+            ],
+            "d": null,
+        }"#,
+        );
+        assert_eq!(Vec::<ObjectValue>::from(object), expected);
+    }
+}
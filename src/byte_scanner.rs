@@ -0,0 +1,511 @@
+//! Byte-oriented scanner that locates JSONC tokens in bulk.
+//!
+//! [ByteScanner] emits exactly the same [Event]s (and errors) as [Scanner],
+//! but operates on the raw bytes of the input rather than decoding every
+//! `char`. Runs of string contents, comments and whitespace are searched eight
+//! bytes at a time using SWAR ("SIMD within a register") operations on `u64`
+//! words, which is portable to every target and requires no `unsafe` code. The
+//! tail of the input that doesn't fill a word is handled by a scalar loop.
+//!
+//! [Scanner]: crate::scanner::Scanner
+
+use crate::{
+    error::Error,
+    escape::{parse_unicode_escape, starts_with_low_surrogate},
+    number::Number,
+    scanner::{filter_metadata, Event, LineEnding, LineEndings, ScanResult, Token},
+};
+
+const WORD: usize = 8;
+const ONES: u64 = 0x0101_0101_0101_0101;
+const LOW_BITS: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// ByteScanner is a lexer for JSON with C-style comments and trailing commas
+/// that is optimized for large inputs. It is itself an `Iterator` over
+/// `ScanResult`s and produces the same output as a
+/// [Scanner](crate::scanner::Scanner) for any input.
+pub struct ByteScanner<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    has_error: bool,
+    line_endings: LineEndings,
+    finite_numbers: bool,
+    reject_lone_surrogates: bool,
+    whitespace: bool,
+}
+
+impl<'a> Iterator for ByteScanner<'a> {
+    type Item = ScanResult<'a>;
+
+    fn next(&mut self) -> Option<ScanResult<'a>> {
+        if self.has_error {
+            None
+        } else {
+            match self.parse_value() {
+                Some(Err(err)) => {
+                    self.has_error = true;
+                    Some(Err(err))
+                }
+                v => v,
+            }
+        }
+    }
+}
+
+impl<'a> ByteScanner<'a> {
+    /// Creates a new ByteScanner from the input string.
+    pub fn new(input: &'a str) -> Self {
+        ByteScanner {
+            input,
+            bytes: input.as_bytes(),
+            pos: 0,
+            has_error: false,
+            line_endings: LineEndings::default(),
+            finite_numbers: false,
+            reject_lone_surrogates: false,
+            whitespace: false,
+        }
+    }
+
+    /// Sets whether numbers that cannot be represented as a finite `f64` are
+    /// rejected. See
+    /// [Scanner::with_finite_numbers](crate::scanner::Scanner::with_finite_numbers).
+    pub fn with_finite_numbers(self, finite_numbers: bool) -> Self {
+        Self {
+            finite_numbers,
+            ..self
+        }
+    }
+
+    /// Sets whether lone surrogate escapes in strings are rejected. See
+    /// [Scanner::with_reject_lone_surrogates](crate::scanner::Scanner::with_reject_lone_surrogates).
+    pub fn with_reject_lone_surrogates(self, reject_lone_surrogates: bool) -> Self {
+        Self {
+            reject_lone_surrogates,
+            ..self
+        }
+    }
+
+    /// Sets whether runs of whitespace are emitted as `Token::Whitespace`
+    /// events. See
+    /// [Scanner::with_whitespace](crate::scanner::Scanner::with_whitespace).
+    pub fn with_whitespace(self, whitespace: bool) -> Self {
+        Self { whitespace, ..self }
+    }
+
+    /// Returns the line ending convention used most often in the input scanned
+    /// so far. See [Scanner::line_ending](crate::scanner::Scanner::line_ending).
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_endings.dominant()
+    }
+
+    /// Return an `Iterator` that filters out all C-style comments, newlines and
+    /// whitespace.
+    pub fn without_metadata(self) -> impl Iterator<Item = ScanResult<'a>> {
+        filter_metadata(self)
+    }
+
+    fn parse_value(&mut self) -> Option<ScanResult<'a>> {
+        let start = self.pos;
+        self.skip_whitespace();
+        if self.whitespace && self.pos > start {
+            return Some(Ok(Event {
+                token: Token::Whitespace(&self.input[start..self.pos]),
+                range: start..self.pos,
+            }));
+        }
+        let start = self.pos;
+        let b = *self.bytes.get(start)?;
+        self.pos += 1;
+        let token = match b {
+            b'\n' => {
+                self.line_endings.add_newline(false);
+                Token::Newline
+            }
+            b'\r' => {
+                // Only reached when followed by '\n', see skip_whitespace.
+                self.pos += 1;
+                self.line_endings.add_newline(true);
+                Token::Newline
+            }
+            b'{' => Token::ObjectStart,
+            b'}' => Token::ObjectEnd,
+            b'[' => Token::ArrayStart,
+            b']' => Token::ArrayEnd,
+            b',' => Token::Comma,
+            b':' => Token::Colon,
+            b'n' => return Some(self.parse_literal(start, "null", Token::Null)),
+            b't' => return Some(self.parse_literal(start, "true", Token::Bool(true))),
+            b'f' => return Some(self.parse_literal(start, "false", Token::Bool(false))),
+            b'/' => return Some(self.parse_comment(start)),
+            b'"' => return Some(self.parse_string(start)),
+            b'-' | b'0'..=b'9' => return Some(self.parse_number(start)),
+            _ => return Some(Err(self.unexpected(start))),
+        };
+        Some(Ok(Event {
+            token,
+            range: start..self.pos,
+        }))
+    }
+
+    fn parse_number(&mut self, start: usize) -> ScanResult<'a> {
+        let first = if self.bytes[start] == b'-' {
+            self.next_digit()?
+        } else {
+            self.bytes[start]
+        };
+        if first != b'0' {
+            self.skip_digits();
+        }
+
+        if self.peek_byte() == Some(b'.') {
+            self.pos += 1;
+            self.next_digit()?;
+            self.skip_digits();
+        }
+
+        if let Some(b'e' | b'E') = self.peek_byte() {
+            self.pos += 1;
+            if let Some(b'-' | b'+') = self.peek_byte() {
+                self.pos += 1;
+            }
+            self.next_digit()?;
+            self.skip_digits();
+        }
+
+        let range = start..self.pos;
+        let number = &self.input[range.clone()];
+        if self.finite_numbers && !Number::new(number).is_finite_f64() {
+            return Err(Error::NumberOutOfRange(range));
+        }
+        Ok(Event {
+            token: Token::Number(number),
+            range,
+        })
+    }
+
+    fn parse_string(&mut self, start: usize) -> ScanResult<'a> {
+        loop {
+            self.pos = find_string_special(self.bytes, self.pos);
+            match self.peek_byte() {
+                Some(b'"') => {
+                    let end = self.pos;
+                    self.pos += 1;
+                    return Ok(Event {
+                        token: Token::String(&self.input[(start + 1)..end]),
+                        range: start..self.pos,
+                    });
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    self.parse_escape()?;
+                }
+                Some(_) => return Err(self.unexpected(self.pos)),
+                None => return Err(Error::UnexpectedEOF),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<(), Error> {
+        match self.peek_byte() {
+            Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(b'u') => {
+                let start = self.pos - 1;
+                self.pos += 1;
+                for _ in 0..4 {
+                    match self.peek_byte() {
+                        Some(b) if b.is_ascii_hexdigit() => self.pos += 1,
+                        Some(_) => return Err(self.unexpected(self.pos)),
+                        None => return Err(Error::UnexpectedEOF),
+                    }
+                }
+                if self.reject_lone_surrogates {
+                    match parse_unicode_escape(&self.bytes[start..]) {
+                        Some(0xD800..=0xDBFF)
+                            if starts_with_low_surrogate(&self.bytes[self.pos..]) =>
+                        {
+                            self.pos += 6;
+                        }
+                        Some(0xD800..=0xDFFF) => {
+                            return Err(Error::LoneSurrogate(start..self.pos));
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected(self.pos)),
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+
+    fn parse_comment(&mut self, start: usize) -> ScanResult<'a> {
+        match self.peek_byte() {
+            Some(b'/') => Ok(self.parse_line_comment(start)),
+            Some(b'*') => self.parse_block_comment(start),
+            Some(_) => Err(self.unexpected(self.pos)),
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+
+    fn parse_line_comment(&mut self, start: usize) -> Event<'a> {
+        let newline = find_byte(self.bytes, start + 2, b'\n');
+        let mut end = newline;
+        if newline < self.bytes.len() && newline > start + 2 && self.bytes[newline - 1] == b'\r' {
+            end = newline - 1;
+        }
+        self.pos = end;
+        Event {
+            token: Token::LineComment(&self.input[(start + 2)..end]),
+            range: start..end,
+        }
+    }
+
+    fn parse_block_comment(&mut self, start: usize) -> ScanResult<'a> {
+        let mut from = start + 2;
+        loop {
+            let i = find_byte(self.bytes, from, b'*');
+            match self.bytes.get(i + 1) {
+                Some(b'/') => {
+                    self.pos = i + 2;
+                    let comment = &self.input[(start + 2)..i];
+                    self.line_endings.add_text(comment);
+                    return Ok(Event {
+                        token: Token::BlockComment(comment),
+                        range: start..self.pos,
+                    });
+                }
+                Some(_) => from = i + 1,
+                None => {
+                    self.pos = self.bytes.len();
+                    return Err(Error::UnexpectedEOF);
+                }
+            }
+        }
+    }
+
+    fn parse_literal(&mut self, start: usize, lit: &str, token: Token<'a>) -> ScanResult<'a> {
+        if self.bytes[start..].starts_with(lit.as_bytes()) {
+            self.pos = start + lit.len();
+            Ok(Event {
+                token,
+                range: start..self.pos,
+            })
+        } else {
+            Err(Error::UnexpectedCharacter(start, self.bytes[start] as char))
+        }
+    }
+
+    fn next_digit(&mut self) -> Result<u8, Error> {
+        match self.peek_byte() {
+            Some(b) if b.is_ascii_digit() => {
+                self.pos += 1;
+                Ok(b)
+            }
+            Some(_) => Err(self.unexpected(self.pos)),
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some(b'0'..=b'9') = self.peek_byte() {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.pos = find_non_space(self.bytes, self.pos);
+            match self.peek_byte() {
+                Some(b'\t' | 0x0B | 0x0C) => self.pos += 1,
+                Some(b'\r') if self.bytes.get(self.pos + 1) != Some(&b'\n') => self.pos += 1,
+                Some(b) if b >= 0x80 => {
+                    let c = self.char_at(self.pos);
+                    if !c.is_whitespace() {
+                        return;
+                    }
+                    self.pos += c.len_utf8();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn char_at(&self, i: usize) -> char {
+        self.input[i..].chars().next().unwrap_or_default()
+    }
+
+    fn unexpected(&self, i: usize) -> Error {
+        Error::UnexpectedCharacter(i, self.char_at(i))
+    }
+}
+
+/// Returns the index of the first byte at or after `from` that terminates a
+/// run of plain string contents: a quote, a backslash or a control character.
+/// Returns the length of `bytes` if there is none.
+fn find_string_special(bytes: &[u8], from: usize) -> usize {
+    find(bytes, from, |x| {
+        eq_bytes(x, b'"') | eq_bytes(x, b'\\') | lt_bytes(x, 0x20)
+    })
+}
+
+/// Returns the index of the first byte equal to `b` at or after `from`, or the
+/// length of `bytes` if there is none.
+fn find_byte(bytes: &[u8], from: usize, b: u8) -> usize {
+    find(bytes, from, |x| eq_bytes(x, b))
+}
+
+/// Returns the index of the first byte that isn't a space at or after `from`,
+/// or the length of `bytes` if there is none.
+fn find_non_space(bytes: &[u8], from: usize) -> usize {
+    find(bytes, from, |x| !eq_bytes(x, b' ') & HIGH_BITS)
+}
+
+/// Returns the index of the first byte at or after `from` that is flagged by
+/// `mask`. The `mask` function is given eight bytes as a little-endian word and
+/// must set the high bit of every byte that matches, and only those bytes.
+#[inline(always)]
+fn find<F: Fn(u64) -> u64>(bytes: &[u8], from: usize, mask: F) -> usize {
+    let mut i = from;
+    while i + WORD <= bytes.len() {
+        let mut word = [0; WORD];
+        word.copy_from_slice(&bytes[i..(i + WORD)]);
+        let m = mask(u64::from_le_bytes(word));
+        if m != 0 {
+            return i + (m.trailing_zeros() / 8) as usize;
+        }
+        i += WORD;
+    }
+    while i < bytes.len() {
+        if mask(bytes[i] as u64) & 0x80 != 0 {
+            return i;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Sets the high bit of every byte in `x` that is equal to `b`.
+#[inline(always)]
+fn eq_bytes(x: u64, b: u8) -> u64 {
+    let x = x ^ (ONES * b as u64);
+    !(((x & LOW_BITS) + LOW_BITS) | x | LOW_BITS)
+}
+
+/// Sets the high bit of every byte in `x` that is less than `n`, which must be
+/// at most 0x80.
+#[inline(always)]
+fn lt_bytes(x: u64, n: u8) -> u64 {
+    !(((x & LOW_BITS) + ONES * (0x80 - n) as u64) | x) & HIGH_BITS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn assert_same(input: &str) {
+        let mut scanner = Scanner::new(input);
+        let expected = scanner.by_ref().collect::<Vec<_>>();
+        let mut byte_scanner = ByteScanner::new(input);
+        let output = byte_scanner.by_ref().collect::<Vec<_>>();
+        assert_eq!(output, expected, "input: {input:?}");
+        assert_eq!(byte_scanner.line_ending(), scanner.line_ending());
+
+        let expected = Scanner::new(input)
+            .with_whitespace(true)
+            .collect::<Vec<_>>();
+        let output = ByteScanner::new(input)
+            .with_whitespace(true)
+            .collect::<Vec<_>>();
+        assert_eq!(output, expected, "input: {input:?}");
+    }
+
+    #[test]
+    fn test_byte_scanner() {
+        let inputs = [
+            "",
+            "   \t  ",
+            "{\"key\": [1, -2.5e+10, true, false, null, {}]}",
+            "// line comment\r\n/* block\n * comment **/ 0",
+            "[\r\n1,\r\r\n2 /* \r\n */ // \r\r\n]\r",
+            "\"a long string that spans more than a few words \\\" \\\\ \\u00e9 é ✓\"",
+            "\u{a0}\u{3000}[ 01, 0.0, -0, 1E5 ]\u{2028}",
+            "{\"k\":\"v\",}   // trailing\r",
+            "\"\u{10FFFE}\u{10FFFF}\"",
+            "\"tab\tinside\"",
+            "\"unterminated",
+            "\"bad \\x escape\"",
+            "\"bad \\u12g4 escape\"",
+            "\"eof \\u12",
+            "/* unterminated */ /*/",
+            "/x",
+            "/",
+            "nul",
+            "truth",
+            "-",
+            "-a",
+            "1.",
+            "1.e",
+            "1e+",
+            "1eé",
+            "@",
+            "é",
+        ];
+        for input in inputs {
+            assert_same(input);
+        }
+
+        let input = r#"["\uD83D\uDE00", "\uDE00", "\uD83D\n", "\uD83D\u12"]"#;
+        for reject in [false, true] {
+            let expected = Scanner::new(input)
+                .with_reject_lone_surrogates(reject)
+                .collect::<Vec<_>>();
+            let output = ByteScanner::new(input)
+                .with_reject_lone_surrogates(reject)
+                .collect::<Vec<_>>();
+            assert_eq!(output, expected);
+        }
+
+        let input = "[1e308, -1e309]";
+        let expected = Scanner::new(input)
+            .with_finite_numbers(true)
+            .collect::<Vec<_>>();
+        let output = ByteScanner::new(input)
+            .with_finite_numbers(true)
+            .collect::<Vec<_>>();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_byte_scanner_generated() {
+        const FRAGMENTS: &[&str] = &[
+            "{", "}", "[", "]", ",", ":", " ", "    ", "\t", "\n", "\r\n", "\r", "null", "true",
+            "false", "0", "-12.5e3", "1", ".", "e", "\"", "\"key\"", "\"é✓ 😀\"", "\\", "\\n",
+            "\\u00", "ab", "//", "/*", "*/", "*", "/", "\u{a0}", "\u{10FFFF}", "\u{1}",
+        ];
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for _ in 0..2000 {
+            let mut input = String::new();
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            for _ in 0..(state % 24) {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                input.push_str(FRAGMENTS[(state % FRAGMENTS.len() as u64) as usize]);
+            }
+            assert_same(&input);
+        }
+    }
+}
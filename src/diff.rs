@@ -0,0 +1,347 @@
+//! Compare two documents by their JSON values rather than their text.
+
+use std::fmt::Write;
+
+use crate::{
+    ast::{json_eq, Comment, Metadata, Root, Value, ValueToken},
+    attached::{Array, Element, Member, Object},
+    error::Error,
+    escape::unescape,
+    format::{write_json_value_compact, Options},
+    patch::Operation,
+    pointer::Pointer,
+};
+
+/// Change is a difference between two documents at a JSON Pointer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<'a> {
+    /// A value was added.
+    Added(Pointer<'a>, Value<'a>),
+    /// A value was removed.
+    Removed(Pointer<'a>, Value<'a>),
+    /// A value was replaced by a different value. Objects and arrays are only
+    /// reported as changed when they are replaced by another type of value;
+    /// otherwise the changes to their contents are reported.
+    Changed(Pointer<'a>, Value<'a>, Value<'a>),
+    /// The comments of a value changed, from the old to the new comments, but
+    /// the value didn't. Only reported if enabled with
+    /// [DiffOptions::with_comments].
+    Comments(Pointer<'a>, Vec<Comment<'a>>, Vec<Comment<'a>>),
+}
+
+impl<'a> Change<'a> {
+    /// Returns the pointer to the value that changed.
+    pub fn pointer(&self) -> &Pointer<'a> {
+        match self {
+            Self::Added(pointer, _)
+            | Self::Removed(pointer, _)
+            | Self::Changed(pointer, _, _)
+            | Self::Comments(pointer, _, _) => pointer,
+        }
+    }
+
+    /// Returns the JSON Patch operation that makes the change, or `None` for
+    /// changes to comments.
+    pub fn to_operation(&self) -> Option<Operation<'a>> {
+        match self {
+            Self::Added(pointer, value) => Some(Operation::add(pointer.clone(), value.clone())),
+            Self::Removed(pointer, _) => Some(Operation::remove(pointer.clone())),
+            Self::Changed(pointer, _, value) => {
+                Some(Operation::replace(pointer.clone(), value.clone()))
+            }
+            Self::Comments(..) => None,
+        }
+    }
+}
+
+/// DiffOptions control which differences are reported by [diff_opts].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DiffOptions {
+    comments: bool,
+}
+
+impl DiffOptions {
+    /// Sets whether changes to the comments of a value are reported. The
+    /// comments of a value are those above it, on the same line, and, for
+    /// objects and arrays, after their last member. The default is false.
+    pub fn with_comments(self, comments: bool) -> Self {
+        Self { comments }
+    }
+}
+
+/// Returns the differences between the documents according to the default
+/// options. See [diff_opts] for details.
+pub fn diff<'a>(old: &Root<'a>, new: &Root<'a>) -> Vec<Change<'a>> {
+    diff_opts(old, new, &DiffOptions::default())
+}
+
+/// Returns the differences between the documents.
+///
+/// Formatting is ignored: strings are compared by their unescaped contents,
+/// numbers by their value, and objects without regard to the order of their
+/// members. Array elements are compared by index, and elements removed from
+/// the end of an array are reported from the last to the first, so that the
+/// changes can be applied in order (see [to_patch]).
+pub fn diff_opts<'a>(old: &Root<'a>, new: &Root<'a>, opts: &DiffOptions) -> Vec<Change<'a>> {
+    let mut differ = Differ {
+        opts: *opts,
+        pointer: Pointer::root(),
+        changes: Vec::new(),
+    };
+    let comments = |root: &Root<'a>| {
+        let mut comments = metadata_comments(&root.meta_above);
+        comments.extend(metadata_comments(&root.meta_below));
+        comments
+    };
+    differ.diff_value(&old.value, &new.value, comments(old), comments(new));
+    differ.changes
+}
+
+/// Returns the JSON Patch that makes the changes. Changes to comments are
+/// skipped.
+pub fn to_patch<'a>(changes: &[Change<'a>]) -> Vec<Operation<'a>> {
+    changes.iter().filter_map(Change::to_operation).collect()
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Writes a summary of the changes to the writer, one line per change. Values
+/// are written as compact JSON. If `color` is true, each line is colored with
+/// ANSI escape codes.
+pub fn render<W: Write>(w: &mut W, changes: &[Change], color: bool) -> Result<(), Error> {
+    let opts = Options::default();
+    for change in changes {
+        let (sign, code) = match change {
+            Change::Added(..) => ('+', GREEN),
+            Change::Removed(..) => ('-', RED),
+            Change::Changed(..) => ('~', YELLOW),
+            Change::Comments(..) => ('#', CYAN),
+        };
+        if color {
+            w.write_str(code)?;
+        }
+        write!(w, "{sign} {}", change.pointer())?;
+        match change {
+            Change::Added(_, value) | Change::Removed(_, value) => {
+                w.write_str(": ")?;
+                write_json_value_compact(w, value, &opts)?;
+            }
+            Change::Changed(_, old, new) => {
+                w.write_str(": ")?;
+                write_json_value_compact(w, old, &opts)?;
+                w.write_str(" -> ")?;
+                write_json_value_compact(w, new, &opts)?;
+            }
+            Change::Comments(..) => w.write_str(": comments changed")?,
+        }
+        if color {
+            w.write_str(RESET)?;
+        }
+        w.write_char('\n')?;
+    }
+    Ok(())
+}
+
+struct Differ<'a> {
+    opts: DiffOptions,
+    pointer: Pointer<'a>,
+    changes: Vec<Change<'a>>,
+}
+
+impl<'a> Differ<'a> {
+    /// Compares two values, along with the comments outside of them.
+    fn diff_value(
+        &mut self,
+        old: &Value<'a>,
+        new: &Value<'a>,
+        mut old_comments: Vec<Comment<'a>>,
+        mut new_comments: Vec<Comment<'a>>,
+    ) {
+        old_comments.extend(old.comments.iter().cloned());
+        new_comments.extend(new.comments.iter().cloned());
+        match (&old.token, &new.token) {
+            (ValueToken::Object(o), ValueToken::Object(n)) => {
+                let (o, n) = (Object::from(o.clone()), Object::from(n.clone()));
+                old_comments.extend(metadata_comments(&o.dangling));
+                new_comments.extend(metadata_comments(&n.dangling));
+                self.diff_comments(old_comments, new_comments);
+                self.diff_objects(&o, &n);
+            }
+            (ValueToken::Array(o), ValueToken::Array(n)) => {
+                let (o, n) = (Array::from(o.clone()), Array::from(n.clone()));
+                old_comments.extend(metadata_comments(&o.dangling));
+                new_comments.extend(metadata_comments(&n.dangling));
+                self.diff_comments(old_comments, new_comments);
+                self.diff_arrays(&o, &n);
+            }
+            _ if json_eq(old, new) => self.diff_comments(old_comments, new_comments),
+            _ => self.changes.push(Change::Changed(
+                self.pointer.clone(),
+                old.clone(),
+                new.clone(),
+            )),
+        }
+    }
+
+    fn diff_comments(&mut self, old: Vec<Comment<'a>>, new: Vec<Comment<'a>>) {
+        if self.opts.comments && old != new {
+            self.changes
+                .push(Change::Comments(self.pointer.clone(), old, new));
+        }
+    }
+
+    fn diff_objects(&mut self, old: &Object<'a>, new: &Object<'a>) {
+        for member in unique_members(old) {
+            let key = unescape(member.key);
+            self.pointer.push(key.clone());
+            match new.get(&key) {
+                Some(other) => self.diff_value(
+                    &member.value,
+                    &other.value,
+                    member_comments(member),
+                    member_comments(other),
+                ),
+                None => self
+                    .changes
+                    .push(Change::Removed(self.pointer.clone(), member.value.clone())),
+            }
+            self.pointer.pop();
+        }
+        for member in unique_members(new) {
+            let key = unescape(member.key);
+            if old.get(&key).is_none() {
+                self.pointer.push(key);
+                self.changes
+                    .push(Change::Added(self.pointer.clone(), member.value.clone()));
+                self.pointer.pop();
+            }
+        }
+    }
+
+    fn diff_arrays(&mut self, old: &Array<'a>, new: &Array<'a>) {
+        let (old, new) = (&old.elements, &new.elements);
+        let common = old.len().min(new.len());
+        for (i, (o, n)) in old.iter().zip(new).enumerate() {
+            self.pointer.push_index(i);
+            self.diff_value(&o.value, &n.value, element_comments(o), element_comments(n));
+            self.pointer.pop();
+        }
+        for i in (common..old.len()).rev() {
+            self.pointer.push_index(i);
+            self.changes
+                .push(Change::Removed(self.pointer.clone(), old[i].value.clone()));
+            self.pointer.pop();
+        }
+        for (i, element) in new.iter().enumerate().skip(common) {
+            self.pointer.push_index(i);
+            self.changes
+                .push(Change::Added(self.pointer.clone(), element.value.clone()));
+            self.pointer.pop();
+        }
+    }
+}
+
+/// Returns the members of the object, skipping any that are overridden by a
+/// later member with the same key.
+fn unique_members<'o, 'a>(object: &'o Object<'a>) -> impl Iterator<Item = &'o Member<'a>> {
+    object.members.iter().filter(|member| {
+        object
+            .get(&unescape(member.key))
+            .is_some_and(|last| std::ptr::eq(last, *member))
+    })
+}
+
+fn member_comments<'a>(member: &Member<'a>) -> Vec<Comment<'a>> {
+    let mut comments = metadata_comments(&member.detached);
+    comments.extend(member.comments.iter().cloned());
+    comments
+}
+
+fn element_comments<'a>(element: &Element<'a>) -> Vec<Comment<'a>> {
+    let mut comments = metadata_comments(&element.detached);
+    comments.extend(element.comments.iter().cloned());
+    comments
+}
+
+fn metadata_comments<'a>(meta: &[Metadata<'a>]) -> Vec<Comment<'a>> {
+    meta.iter()
+        .filter_map(|m| match m {
+            Metadata::Comment(c) => Some(c.clone()),
+            Metadata::Newline => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::parse,
+        patch::{apply_patch, write_patch, Arena},
+    };
+
+    const OLD: &str = r#"{
+        // The service name.
+        "name": "api",
+        "debug": true,
+        "ports": [80, 443, 8080],
+        "db": {"host": "localhost", "port": 5432},
+    }"#;
+
+    const NEW: &str = r#"// Production.
+    {
+        "db": {"port": 5432.0, "host": "db.internal"},
+        "name": "api", // Renamed comment.
+        "ports": [80, "443"],
+        "replicas": 3,
+    }"#;
+
+    #[test]
+    fn test_diff() {
+        let (old, new) = (parse(OLD).unwrap(), parse(NEW).unwrap());
+        let changes = diff(&old, &new);
+        let mut out = String::new();
+        render(&mut out, &changes, false).unwrap();
+        let expected = r#"- /debug: true
+~ /ports/1: 443 -> "443"
+- /ports/2: 8080
+~ /db/host: "localhost" -> "db.internal"
++ /replicas: 3
+"#;
+        assert_eq!(out, expected);
+
+        let mut colored = String::new();
+        render(&mut colored, &changes[..1], true).unwrap();
+        assert_eq!(colored, "\x1b[31m- /debug: true\x1b[0m\n");
+
+        let changes = diff_opts(&old, &new, &DiffOptions::default().with_comments(true));
+        let comments: Vec<_> = changes
+            .iter()
+            .filter(|c| matches!(c, Change::Comments(..)))
+            .map(|c| c.pointer().to_string())
+            .collect();
+        assert_eq!(comments, ["", "/name"]);
+    }
+
+    #[test]
+    fn test_diff_to_patch() {
+        let (old, new) = (parse(OLD).unwrap(), parse(NEW).unwrap());
+        let ops = to_patch(&diff(&old, &new));
+        let mut out = String::new();
+        write_patch(&mut out, &ops).unwrap();
+        assert_eq!(
+            out,
+            r#"[{"op":"remove","path":"/debug"},{"op":"replace","path":"/ports/1","value":"443"},{"op":"remove","path":"/ports/2"},{"op":"replace","path":"/db/host","value":"db.internal"},{"op":"add","path":"/replicas","value":3}]"#
+        );
+
+        let keys = Arena::new();
+        let mut root = parse(OLD).unwrap();
+        apply_patch(&mut root, &ops, &keys).unwrap();
+        assert!(json_eq(&root.value, &new.value));
+        assert!(diff(&root, &new).is_empty());
+    }
+}
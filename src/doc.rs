@@ -0,0 +1,343 @@
+//! A document algebra for laying out text within a line length, in the style
+//! of Wadler's "A prettier printer".
+
+use std::{borrow::Cow, fmt::Write};
+
+use unicode_width::UnicodeWidthChar;
+
+/// Doc describes text along with the places that it may be broken into lines.
+#[derive(Clone, Debug)]
+pub(crate) enum Doc<'a> {
+    /// Text without newlines.
+    Text(Cow<'a, str>),
+    /// A space, or a newline if the enclosing group is broken.
+    Line,
+    /// Nothing, or a newline if the enclosing group is broken.
+    SoftLine,
+    /// A newline that is always written.
+    HardLine,
+    /// A newline that is always written, without indentation after it.
+    LiteralLine,
+    Concat(Vec<Doc<'a>>),
+    /// Indents the lines that start inside of the document by one level.
+    Indent(Box<Doc<'a>>),
+    /// Writes the document on the current line if it fits, and otherwise
+    /// breaks the lines directly inside of it.
+    Group(Box<Doc<'a>>),
+    /// Alternating contents and separators. Each separator is only broken if
+    /// the contents after it don't fit on the current line.
+    Fill(Vec<Doc<'a>>),
+    /// A document that is only written if the enclosing group is broken.
+    IfBreak(Box<Doc<'a>>),
+    /// A document that isn't counted when deciding whether a group fits, such
+    /// as a comment at the end of a line.
+    Suffix(Box<Doc<'a>>),
+}
+
+impl<'a> Doc<'a> {
+    pub(crate) fn text(s: impl Into<Cow<'a, str>>) -> Self {
+        Self::Text(s.into())
+    }
+
+    pub(crate) fn indent(doc: Doc<'a>) -> Self {
+        Self::Indent(Box::new(doc))
+    }
+
+    pub(crate) fn group(doc: Doc<'a>) -> Self {
+        Self::Group(Box::new(doc))
+    }
+
+    pub(crate) fn if_break(doc: Doc<'a>) -> Self {
+        Self::IfBreak(Box::new(doc))
+    }
+
+    pub(crate) fn suffix(doc: Doc<'a>) -> Self {
+        Self::Suffix(Box::new(doc))
+    }
+
+    /// Returns the width of the document written on a single line, or `None`
+    /// if it contains a newline that is always written. Suffixes aren't
+    /// counted.
+    pub(crate) fn flat_width(&self, tab_width: usize) -> Option<usize> {
+        match self {
+            Doc::Text(s) => Some(advance(0, s, tab_width)),
+            Doc::Line => Some(1),
+            Doc::SoftLine | Doc::IfBreak(_) | Doc::Suffix(_) => Some(0),
+            Doc::HardLine | Doc::LiteralLine => None,
+            Doc::Concat(docs) | Doc::Fill(docs) => docs
+                .iter()
+                .try_fold(0, |width, doc| Some(width + doc.flat_width(tab_width)?)),
+            Doc::Indent(doc) | Doc::Group(doc) => doc.flat_width(tab_width),
+        }
+    }
+}
+
+/// Returns the column after writing the string starting at `column`. Tabs
+/// advance to the next tab stop, and control characters have no width.
+pub(crate) fn advance(column: usize, s: &str, tab_width: usize) -> usize {
+    s.chars().fold(column, |column, c| match c {
+        '\t' if tab_width > 0 => (column / tab_width + 1) * tab_width,
+        c => column + c.width().unwrap_or(0),
+    })
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Item<'d, 'a> {
+    Doc(&'d Doc<'a>),
+    /// The remaining contents and separators of a [Doc::Fill].
+    Fill(&'d [Doc<'a>]),
+}
+
+/// A command to write an item at an indentation level in a mode.
+type Cmd<'d, 'a> = (usize, Mode, Item<'d, 'a>);
+
+/// Printer writes documents, breaking groups that don't fit within the line
+/// length.
+pub(crate) struct Printer<'w, W: Write> {
+    pub(crate) w: &'w mut W,
+    pub(crate) line_length: usize,
+    pub(crate) indent: &'w str,
+    pub(crate) tab_width: usize,
+    pub(crate) newline: &'w str,
+}
+
+impl<W: Write> Printer<'_, W> {
+    pub(crate) fn print(&mut self, doc: &Doc) -> Result<(), std::fmt::Error> {
+        let mut column = 0;
+        // Indentation is only written before text, so that blank lines don't
+        // have trailing whitespace.
+        let mut pending_indent = None;
+        let mut cmds: Vec<Cmd> = vec![(0, Mode::Break, Item::Doc(doc))];
+        while let Some((indent, mode, item)) = cmds.pop() {
+            let doc = match item {
+                Item::Doc(doc) => doc,
+                Item::Fill(parts) => {
+                    self.fill(&mut cmds, column, (indent, mode), parts);
+                    continue;
+                }
+            };
+            let text = match doc {
+                Doc::Text(s) => s.as_ref(),
+                Doc::Line if mode == Mode::Flat => " ",
+                Doc::IfBreak(_) | Doc::SoftLine if mode == Mode::Flat => "",
+                Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                    self.w.write_str(self.newline)?;
+                    column = self.indent_width(indent);
+                    pending_indent = Some(indent);
+                    continue;
+                }
+                Doc::LiteralLine => {
+                    self.w.write_str(self.newline)?;
+                    column = 0;
+                    pending_indent = None;
+                    continue;
+                }
+                Doc::Concat(docs) => {
+                    cmds.extend(docs.iter().rev().map(|d| (indent, mode, Item::Doc(d))));
+                    continue;
+                }
+                Doc::Indent(doc) => {
+                    cmds.push((indent + 1, mode, Item::Doc(doc)));
+                    continue;
+                }
+                Doc::IfBreak(doc) => {
+                    cmds.push((indent, mode, Item::Doc(doc)));
+                    continue;
+                }
+                Doc::Group(doc) => {
+                    let flat = (indent, Mode::Flat, Item::Doc(doc));
+                    let fits = mode == Mode::Flat
+                        || self.fits(vec![flat], &cmds, self.remaining(column), false);
+                    cmds.push(if fits {
+                        flat
+                    } else {
+                        (indent, Mode::Break, Item::Doc(doc))
+                    });
+                    continue;
+                }
+                Doc::Fill(parts) => {
+                    cmds.push((indent, mode, Item::Fill(parts)));
+                    continue;
+                }
+                Doc::Suffix(doc) => {
+                    cmds.push((indent, Mode::Break, Item::Doc(doc)));
+                    continue;
+                }
+            };
+            if text.is_empty() {
+                continue;
+            }
+            if let Some(n) = pending_indent.take() {
+                for _ in 0..n {
+                    self.w.write_str(self.indent)?;
+                }
+            }
+            self.w.write_str(text)?;
+            column = advance(column, text, self.tab_width);
+        }
+        Ok(())
+    }
+
+    /// Queues the next contents of a fill, and the separator after them,
+    /// breaking the separator if the contents that follow it don't fit. The
+    /// contents are written in the mode of the fill, so that they include
+    /// the [Doc::IfBreak]s of a broken group.
+    fn fill<'d, 'a>(
+        &self,
+        cmds: &mut Vec<Cmd<'d, 'a>>,
+        column: usize,
+        (indent, mode): (usize, Mode),
+        parts: &'d [Doc<'a>],
+    ) {
+        let remaining = self.remaining(column);
+        let [contents, rest @ ..] = parts else {
+            return;
+        };
+        let contents = (indent, mode, Item::Doc(contents));
+        let contents = if self.fits(vec![contents], &[], remaining, true) {
+            contents
+        } else {
+            (indent, Mode::Break, contents.2)
+        };
+        let [separator, rest @ ..] = rest else {
+            cmds.push(contents);
+            return;
+        };
+        // Measure the contents, separator, and next contents, from last to
+        // first since they're popped off of the end.
+        let separator_mode = match rest.first() {
+            Some(next)
+                if !self.fits(
+                    vec![
+                        (indent, mode, Item::Doc(next)),
+                        (indent, Mode::Flat, Item::Doc(separator)),
+                        contents,
+                    ],
+                    &[],
+                    remaining,
+                    true,
+                ) =>
+            {
+                Mode::Break
+            }
+            _ => Mode::Flat,
+        };
+        cmds.push((indent, mode, Item::Fill(rest)));
+        cmds.push((indent, separator_mode, Item::Doc(separator)));
+        cmds.push(contents);
+    }
+
+    /// Returns whether the commands fit within the width, up to the first
+    /// line that is broken. If `must_be_flat` is false, the commands in
+    /// `rest` are measured after `next`.
+    fn fits<'d, 'a>(
+        &self,
+        mut next: Vec<Cmd<'d, 'a>>,
+        rest: &[Cmd<'d, 'a>],
+        width: isize,
+        must_be_flat: bool,
+    ) -> bool {
+        let mut width = width;
+        let mut rest = rest.iter().rev();
+        loop {
+            if width < 0 {
+                return false;
+            }
+            let Some((indent, mode, item)) = next.pop().or_else(|| {
+                if must_be_flat {
+                    None
+                } else {
+                    rest.next().copied()
+                }
+            }) else {
+                return true;
+            };
+            let doc = match item {
+                Item::Doc(doc) => doc,
+                Item::Fill(parts) => {
+                    next.extend(parts.iter().rev().map(|d| (indent, mode, Item::Doc(d))));
+                    continue;
+                }
+            };
+            match doc {
+                Doc::Text(s) => width -= advance(0, s, self.tab_width) as isize,
+                Doc::Line if mode == Mode::Flat => width -= 1,
+                Doc::IfBreak(_) | Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Suffix(_) => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::LiteralLine => return true,
+                Doc::Concat(docs) | Doc::Fill(docs) => {
+                    next.extend(docs.iter().rev().map(|d| (indent, mode, Item::Doc(d))));
+                }
+                Doc::Indent(doc) | Doc::Group(doc) | Doc::IfBreak(doc) => {
+                    next.push((indent, mode, Item::Doc(doc)))
+                }
+            }
+        }
+    }
+
+    fn remaining(&self, column: usize) -> isize {
+        self.line_length as isize - column as isize
+    }
+
+    fn indent_width(&self, n: usize) -> usize {
+        (0..n).fold(0, |column, _| advance(column, self.indent, self.tab_width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(doc: &Doc, line_length: usize) -> String {
+        let mut out = String::new();
+        let mut printer = Printer {
+            w: &mut out,
+            line_length,
+            indent: "  ",
+            tab_width: 4,
+            newline: "\n",
+        };
+        printer.print(doc).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_group_and_fill() {
+        let list = |items: &[&'static str]| {
+            let mut docs = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    docs.extend([Doc::text(","), Doc::Line]);
+                }
+                docs.push(Doc::text(*item));
+            }
+            Doc::group(Doc::Concat(vec![
+                Doc::text("["),
+                Doc::indent(Doc::Concat(vec![Doc::SoftLine, Doc::Concat(docs)])),
+                Doc::if_break(Doc::text(",")),
+                Doc::SoftLine,
+                Doc::text("]"),
+            ]))
+        };
+        let doc = list(&["1", "2", "3"]);
+        assert_eq!(print(&doc, 9), "[1, 2, 3]");
+        assert_eq!(print(&doc, 8), "[\n  1,\n  2,\n  3,\n]");
+        assert_eq!(doc.flat_width(4), Some(9));
+
+        let fill = Doc::Fill(vec![
+            Doc::text("1,"),
+            Doc::Line,
+            Doc::text("22,"),
+            Doc::Line,
+            Doc::text("333"),
+        ]);
+        assert_eq!(print(&fill, 8), "1, 22,\n333");
+        assert_eq!(print(&fill, 10), "1, 22, 333");
+    }
+}
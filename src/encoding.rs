@@ -0,0 +1,97 @@
+//! Decode raw JSONC bytes into a string.
+
+use std::borrow::Cow;
+
+use crate::error::Error;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Decodes the provided bytes into a string that can be parsed.
+///
+/// A leading UTF-8 byte order mark is skipped, and the remaining input is
+/// borrowed if it is valid UTF-8. Input starting with a UTF-16 (little or big
+/// endian) byte order mark is transcoded into an owned string. Any invalid
+/// input is reported with the byte index where decoding failed.
+///
+/// Note: indexes reported when parsing the returned string are relative to the
+/// decoded string, not the original bytes.
+pub fn decode(input: &[u8]) -> Result<Cow<'_, str>, Error> {
+    if let Some(rest) = input.strip_prefix(UTF8_BOM) {
+        decode_utf8(rest, UTF8_BOM.len()).map(Cow::Borrowed)
+    } else if let Some(rest) = input.strip_prefix(UTF16LE_BOM) {
+        decode_utf16(rest, UTF16LE_BOM.len(), u16::from_le_bytes).map(Cow::Owned)
+    } else if let Some(rest) = input.strip_prefix(UTF16BE_BOM) {
+        decode_utf16(rest, UTF16BE_BOM.len(), u16::from_be_bytes).map(Cow::Owned)
+    } else {
+        decode_utf8(input, 0).map(Cow::Borrowed)
+    }
+}
+
+fn decode_utf8(input: &[u8], offset: usize) -> Result<&str, Error> {
+    std::str::from_utf8(input).map_err(|err| Error::InvalidUtf8(offset + err.valid_up_to()))
+}
+
+fn decode_utf16(input: &[u8], offset: usize, f: fn([u8; 2]) -> u16) -> Result<String, Error> {
+    let mut out = String::with_capacity(input.len() / 2);
+    let mut units = input
+        .chunks(2)
+        .enumerate()
+        .map(|(i, chunk)| (offset + i * 2, chunk))
+        .peekable();
+    while let Some((i, chunk)) = units.next() {
+        let unit = match chunk {
+            [a, b] => f([*a, *b]),
+            _ => return Err(Error::InvalidUtf16(i)),
+        };
+        let c = match unit {
+            0xD800..=0xDBFF => match units.peek() {
+                Some(&(_, [a, b])) => {
+                    let low = f([*a, *b]);
+                    if !(0xDC00..=0xDFFF).contains(&low) {
+                        return Err(Error::InvalidUtf16(i));
+                    }
+                    units.next();
+                    let c = 0x10000 + (((unit as u32) - 0xD800) << 10) + ((low as u32) - 0xDC00);
+                    char::from_u32(c).ok_or(Error::InvalidUtf16(i))?
+                }
+                _ => return Err(Error::InvalidUtf16(i)),
+            },
+            unit => char::from_u32(unit as u32).ok_or(Error::InvalidUtf16(i))?,
+        };
+        out.push(c);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"{}").unwrap(), Cow::Borrowed("{}"));
+        assert_eq!(decode(b"\xEF\xBB\xBF{}").unwrap(), Cow::Borrowed("{}"));
+
+        let utf16le = [0xFF, 0xFE, b'[', 0, 0x3D, 0xD8, 0x00, 0xDE, b']', 0];
+        assert_eq!(decode(&utf16le).unwrap(), "[😀]");
+        let utf16be = [0xFE, 0xFF, 0, b'[', 0, 0xE9, 0, b']'];
+        assert_eq!(decode(&utf16be).unwrap(), "[é]");
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(decode(b"[\"\xFF\"]"), Err(Error::InvalidUtf8(2)));
+        assert_eq!(decode(b"\xEF\xBB\xBF[\xC3]"), Err(Error::InvalidUtf8(4)));
+        assert_eq!(decode(&[0xFF, 0xFE, b'[', 0, b']']), Err(Error::InvalidUtf16(4)));
+        assert_eq!(
+            decode(&[0xFF, 0xFE, b'[', 0, 0x3D, 0xD8, b']', 0]),
+            Err(Error::InvalidUtf16(4))
+        );
+        assert_eq!(
+            decode(&[0xFE, 0xFF, 0, b'[', 0xDE, 0x00]),
+            Err(Error::InvalidUtf16(4))
+        );
+    }
+}
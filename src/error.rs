@@ -6,7 +6,10 @@ use std::{
     ops::Range,
 };
 
-use crate::scanner::{Event, Token};
+use crate::{
+    patch::PatchError,
+    scanner::{Event, Token},
+};
 
 /// The error type used in this crate.
 #[derive(Clone, Debug, PartialEq)]
@@ -19,6 +22,19 @@ pub enum Error {
     UnexpectedToken(Range<usize>, TokenType),
     /// The end-of-file was reached while parsing the JSON source.
     UnexpectedEOF,
+    /// A number could not be represented as a finite 64-bit float.
+    NumberOutOfRange(Range<usize>),
+    /// A string contains an escaped UTF-16 surrogate that isn't part of a
+    /// valid pair.
+    LoneSurrogate(Range<usize>),
+    /// A JSON Pointer is malformed at the provided index.
+    InvalidPointer(usize),
+    /// The JSON Patch operation at the provided index failed for the pointer.
+    Patch(usize, String, PatchError),
+    /// The input bytes are not valid UTF-8, starting at the provided index.
+    InvalidUtf8(usize),
+    /// The input bytes are not valid UTF-16, starting at the provided index.
+    InvalidUtf16(usize),
     /// Error formatting the JSON to the std::fmt::Writer provided.
     Write(fmt::Error),
 }
@@ -48,6 +64,26 @@ impl fmt::Display for Error {
                 )
             }
             Self::UnexpectedEOF => f.write_str("unexpected end of file"),
+            Self::NumberOutOfRange(range) => {
+                write!(
+                    f,
+                    "number out of range at index range {} -> {}",
+                    range.start, range.end
+                )
+            }
+            Self::LoneSurrogate(range) => {
+                write!(
+                    f,
+                    "lone surrogate at index range {} -> {}",
+                    range.start, range.end
+                )
+            }
+            Self::InvalidPointer(i) => write!(f, "invalid JSON pointer at index {i}"),
+            Self::Patch(i, pointer, err) => {
+                write!(f, "patch operation {i} failed at '{pointer}': {err}")
+            }
+            Self::InvalidUtf8(i) => write!(f, "invalid utf-8 at index {i}"),
+            Self::InvalidUtf16(i) => write!(f, "invalid utf-16 at index {i}"),
             Self::Write(err) => write!(f, "writing: {err}"),
         }
     }
@@ -87,6 +123,7 @@ pub enum TokenType {
     String,
     Number,
     Bool,
+    Whitespace,
 }
 
 impl std::convert::From<Token<'_>> for TokenType {
@@ -105,6 +142,7 @@ impl std::convert::From<Token<'_>> for TokenType {
             Token::String(_) => TokenType::String,
             Token::Number(_) => TokenType::Number,
             Token::Bool(_) => TokenType::Bool,
+            Token::Whitespace(_) => TokenType::Whitespace,
         }
     }
 }
@@ -125,6 +163,7 @@ impl Display for TokenType {
             TokenType::String => "string",
             TokenType::Number => "number",
             TokenType::Bool => "bool",
+            TokenType::Whitespace => "whitespace",
         };
         f.write_str(out)
     }
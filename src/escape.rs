@@ -0,0 +1,196 @@
+//! Helpers for working with the escaped contents of JSON strings.
+
+use std::{borrow::Cow, fmt::Write};
+
+/// Piece is a part of the raw contents of a JSON string.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Piece<'a> {
+    /// A run of characters without any escape sequences.
+    Literal(&'a str),
+    /// An escape sequence, including the leading backslash, and its value.
+    Escape(&'a str, Escaped),
+}
+
+/// Escaped is the decoded value of an escape sequence.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Escaped {
+    Char(char),
+    /// A `\u` escape of a UTF-16 surrogate that isn't part of a valid pair.
+    LoneSurrogate(u16),
+}
+
+/// Returns an `Iterator` over the `Piece`s of the raw contents of a JSON
+/// string, as emitted by the Scanner.
+pub(crate) fn pieces(s: &str) -> Pieces<'_> {
+    Pieces { s }
+}
+
+pub(crate) struct Pieces<'a> {
+    s: &'a str,
+}
+
+impl<'a> Iterator for Pieces<'a> {
+    type Item = Piece<'a>;
+
+    fn next(&mut self) -> Option<Piece<'a>> {
+        if self.s.is_empty() {
+            return None;
+        }
+        let (piece, len) = match parse_escape(self.s.as_bytes()) {
+            Some((escaped, len)) => (Piece::Escape(&self.s[..len], escaped), len),
+            None => {
+                // Malformed escapes (which the Scanner never emits) are
+                // treated as literal text.
+                let len = match self.s.find('\\') {
+                    Some(0) => self.s[1..].find('\\').map_or(self.s.len(), |i| i + 1),
+                    Some(i) => i,
+                    None => self.s.len(),
+                };
+                (Piece::Literal(&self.s[..len]), len)
+            }
+        };
+        self.s = &self.s[len..];
+        Some(piece)
+    }
+}
+
+/// Returns the unescaped contents of a JSON string. Lone surrogates are
+/// replaced with U+FFFD.
+pub(crate) fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    for piece in pieces(s) {
+        match piece {
+            Piece::Literal(v) => out.push_str(v),
+            Piece::Escape(_, Escaped::Char(c)) => out.push(c),
+            Piece::Escape(_, Escaped::LoneSurrogate(_)) => out.push('\u{FFFD}'),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Returns the raw contents of a JSON string with the value `s`. Quotes,
+/// backslashes, and control characters are escaped.
+pub(crate) fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(|c: char| c == '"' || c == '\\' || c.is_ascii_control()) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        // Writing to a String can't fail.
+        let _ = write_escaped(&mut out, c, false);
+    }
+    Cow::Owned(out)
+}
+
+/// Writes the character as part of the raw contents of a JSON string,
+/// escaping quotes, backslashes, and control characters. If `ascii` is true,
+/// non-ASCII characters are also escaped, as surrogate pairs if they're
+/// outside of the Basic Multilingual Plane.
+pub(crate) fn write_escaped<W: Write>(w: &mut W, c: char, ascii: bool) -> std::fmt::Result {
+    match c {
+        '"' => w.write_str("\\\""),
+        '\\' => w.write_str("\\\\"),
+        '\u{8}' => w.write_str("\\b"),
+        '\u{c}' => w.write_str("\\f"),
+        '\n' => w.write_str("\\n"),
+        '\r' => w.write_str("\\r"),
+        '\t' => w.write_str("\\t"),
+        c if c.is_ascii_control() || (ascii && !c.is_ascii()) => {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                write!(w, "\\u{unit:04x}")?;
+            }
+            Ok(())
+        }
+        c => w.write_char(c),
+    }
+}
+
+/// Returns the UTF-16 code unit of the `\uXXXX` escape at the start of `b`.
+pub(crate) fn parse_unicode_escape(b: &[u8]) -> Option<u16> {
+    match b {
+        [b'\\', b'u', hex @ ..] if hex.len() >= 4 => {
+            let hex = std::str::from_utf8(&hex[..4]).ok()?;
+            if hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                u16::from_str_radix(hex, 16).ok()
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if `b` starts with a `\uXXXX` escape of a low surrogate.
+pub(crate) fn starts_with_low_surrogate(b: &[u8]) -> bool {
+    matches!(parse_unicode_escape(b), Some(0xDC00..=0xDFFF))
+}
+
+fn parse_escape(b: &[u8]) -> Option<(Escaped, usize)> {
+    let c = match b {
+        [b'\\', b'u', ..] => {
+            let unit = parse_unicode_escape(b)?;
+            return match unit {
+                0xD800..=0xDBFF => match parse_unicode_escape(&b[6..]) {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        let c = 0x10000 + (((unit as u32) - 0xD800) << 10) + (low as u32 - 0xDC00);
+                        Some((Escaped::Char(char::from_u32(c)?), 12))
+                    }
+                    _ => Some((Escaped::LoneSurrogate(unit), 6)),
+                },
+                0xDC00..=0xDFFF => Some((Escaped::LoneSurrogate(unit), 6)),
+                _ => Some((Escaped::Char(char::from_u32(unit as u32)?), 6)),
+            };
+        }
+        [b'\\', b'"', ..] => '"',
+        [b'\\', b'\\', ..] => '\\',
+        [b'\\', b'/', ..] => '/',
+        [b'\\', b'b', ..] => '\u{8}',
+        [b'\\', b'f', ..] => '\u{c}',
+        [b'\\', b'n', ..] => '\n',
+        [b'\\', b'r', ..] => '\r',
+        [b'\\', b't', ..] => '\t',
+        _ => return None,
+    };
+    Some((Escaped::Char(c), 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces() {
+        let out = pieces(r#"a\"b\u00e9\uD83D\uDE00\uD800\uDC0Ac\\"#).collect::<Vec<_>>();
+        assert_eq!(
+            out,
+            vec![
+                Piece::Literal("a"),
+                Piece::Escape(r#"\""#, Escaped::Char('"')),
+                Piece::Literal("b"),
+                Piece::Escape(r"\u00e9", Escaped::Char('é')),
+                Piece::Escape(r"\uD83D\uDE00", Escaped::Char('😀')),
+                Piece::Escape(r"\uD800\uDC0A", Escaped::Char('\u{1000a}')),
+                Piece::Literal("c"),
+                Piece::Escape(r"\\", Escaped::Char('\\')),
+            ]
+        );
+
+        let out = pieces(r"\ud800\ud800x\udc00").collect::<Vec<_>>();
+        assert_eq!(
+            out,
+            vec![
+                Piece::Escape(r"\ud800", Escaped::LoneSurrogate(0xD800)),
+                Piece::Escape(r"\ud800", Escaped::LoneSurrogate(0xD800)),
+                Piece::Literal("x"),
+                Piece::Escape(r"\udc00", Escaped::LoneSurrogate(0xDC00)),
+            ]
+        );
+
+        assert_eq!(unescape("plain"), Cow::Borrowed("plain"));
+        assert_eq!(unescape(r"a\nb\ud800"), "a\nb\u{FFFD}");
+    }
+}
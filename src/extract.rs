@@ -0,0 +1,229 @@
+//! Extract a single value from JSONC source without parsing the rest.
+
+use std::{borrow::Cow, ops::Range};
+
+use crate::{
+    byte_scanner::ByteScanner,
+    error::Error,
+    escape::unescape,
+    pointer::{parse_index, Pointer},
+    scanner::{filter_metadata, Event, ScanResult, Token},
+};
+
+/// Subtree is the source of a value found with [extract].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtree<'a> {
+    pub source: &'a str,
+    pub range: Range<usize>,
+}
+
+/// Returns the source of the value at `pointer` in the input, or `None` if
+/// there is no such value. See [find] for details.
+pub fn extract<'a>(input: &'a str, pointer: &Pointer) -> Result<Option<Subtree<'a>>, Error> {
+    let range = find(ByteScanner::new(input), pointer)?;
+    Ok(range.map(|range| Subtree {
+        source: &input[range.clone()],
+        range,
+    }))
+}
+
+/// Returns the range in the source of the value at `pointer`, or `None` if
+/// there is no such value.
+///
+/// Values other than the target are skipped by matching brackets, without
+/// building AST nodes. Only the structure along the path to the target is
+/// validated, and the input after the root value is not read at all. If an
+/// object has duplicate keys, the last is used, as with
+/// [Object::get](crate::attached::Object::get).
+pub fn find<'a, I>(iter: I, pointer: &Pointer) -> Result<Option<Range<usize>>, Error>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    let mut seek = Seek {
+        iter: filter_metadata(iter),
+    };
+    let event = seek.next_event()?;
+    seek.seek(event, pointer.tokens())
+}
+
+struct Seek<I> {
+    iter: I,
+}
+
+impl<'a, I> Seek<I>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    /// Returns the range of the value at `tokens` inside of the value
+    /// starting with `event`. The whole value is consumed.
+    fn seek(
+        &mut self,
+        event: Event<'a>,
+        tokens: &[Cow<str>],
+    ) -> Result<Option<Range<usize>>, Error> {
+        let Some((token, rest)) = tokens.split_first() else {
+            let start = event.range.start;
+            let end = self.skip_value(event)?;
+            return Ok(Some(start..end));
+        };
+        match event.token {
+            Token::ObjectStart => self.seek_key(token, rest),
+            Token::ArrayStart => self.seek_index(parse_index(token), rest),
+            _ => {
+                self.skip_value(event)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the range of the value at `rest` inside of the last value of
+    /// `key` in the current object. The rest of the object is consumed, since
+    /// a later duplicate key takes the place of an earlier one.
+    fn seek_key(&mut self, key: &str, rest: &[Cow<str>]) -> Result<Option<Range<usize>>, Error> {
+        let mut found = None;
+        loop {
+            let event = self.next_event()?;
+            let k = match event.token {
+                Token::ObjectEnd => return Ok(found),
+                Token::String(k) => k,
+                _ => return Err(event.into()),
+            };
+            let event = self.next_event()?;
+            if event.token != Token::Colon {
+                return Err(event.into());
+            }
+            let value = self.next_event()?;
+            if unescape(k) == key {
+                found = self.seek(value, rest)?;
+            } else {
+                self.skip_value(value)?;
+            }
+            if !self.next_separator(Token::ObjectEnd)? {
+                return Ok(found);
+            }
+        }
+    }
+
+    /// Returns the range of the value at `rest` inside of the value at `index`
+    /// in the current array. The rest of the array is consumed.
+    fn seek_index(
+        &mut self,
+        index: Option<usize>,
+        rest: &[Cow<str>],
+    ) -> Result<Option<Range<usize>>, Error> {
+        let mut found = None;
+        for i in 0.. {
+            let event = self.next_event()?;
+            if event.token == Token::ArrayEnd {
+                break;
+            }
+            if Some(i) == index {
+                found = self.seek(event, rest)?;
+            } else {
+                self.skip_value(event)?;
+            }
+            if !self.next_separator(Token::ArrayEnd)? {
+                break;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Skips the value starting with `event`, returning the index where it
+    /// ends.
+    fn skip_value(&mut self, event: Event<'a>) -> Result<usize, Error> {
+        let mut open = Vec::new();
+        let mut event = event;
+        loop {
+            match event.token {
+                Token::ObjectStart => open.push(Token::ObjectEnd),
+                Token::ArrayStart => open.push(Token::ArrayEnd),
+                Token::ObjectEnd | Token::ArrayEnd => {
+                    if open.pop() != Some(event.token) {
+                        return Err(event.into());
+                    }
+                }
+                Token::String(_) | Token::Number(_) | Token::Bool(_) | Token::Null => {}
+                _ if !open.is_empty() => {}
+                _ => return Err(event.into()),
+            }
+            if open.is_empty() {
+                return Ok(event.range.end);
+            }
+            event = self.next_event()?;
+        }
+    }
+
+    /// Consumes a comma or the `end` token, returning true for a comma.
+    fn next_separator(&mut self, end: Token) -> Result<bool, Error> {
+        let event = self.next_event()?;
+        match event.token {
+            Token::Comma => Ok(true),
+            token if token == end => Ok(false),
+            _ => Err(event.into()),
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Event<'a>, Error> {
+        match self.iter.next() {
+            Some(result) => result,
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::TokenType, scanner::Scanner};
+
+    #[test]
+    fn test_extract() {
+        let input = r#"// Config.
+        {
+            "name": "fjson", // Name.
+            "a/b": {"list": [1, [2, 3], {"k": null},]},
+            "name": "duplicate",
+        }"#;
+        let cases = [
+            ("", &input[19..]),
+            ("/name", r#""duplicate""#),
+            ("/a~1b", r#"{"list": [1, [2, 3], {"k": null},]}"#),
+            ("/a~1b/list/1", "[2, 3]"),
+            ("/a~1b/list/2/k", "null"),
+        ];
+        for (pointer, expected) in cases {
+            let pointer = Pointer::parse(pointer).unwrap();
+            let subtree = extract(input, &pointer).unwrap().unwrap();
+            assert_eq!(subtree.source, expected);
+            assert_eq!(&input[subtree.range.clone()], expected);
+            assert_eq!(find(Scanner::new(input), &pointer), Ok(Some(subtree.range)));
+        }
+
+        for pointer in ["/missing", "/name/0", "/a~1b/list/3", "/a~1b/list/01"] {
+            let pointer = Pointer::parse(pointer).unwrap();
+            assert_eq!(extract(input, &pointer), Ok(None));
+        }
+    }
+
+    #[test]
+    fn test_extract_invalid() {
+        let pointer = Pointer::parse("/b").unwrap();
+        // Skipped values are only checked for matching brackets.
+        let subtree = extract(r#"{"a": [1 2 :], "b": 3} trailing"#, &pointer).unwrap();
+        assert_eq!(subtree.map(|s| s.source), Some("3"));
+
+        assert_eq!(
+            extract(r#"{"a": [1}, "b": 3}"#, &pointer),
+            Err(Error::UnexpectedToken(8..9, TokenType::ObjectEnd))
+        );
+        assert_eq!(
+            extract(r#"{"a": 1 "b": 3}"#, &pointer),
+            Err(Error::UnexpectedToken(8..11, TokenType::String))
+        );
+        assert_eq!(
+            extract(r#"{"a": [1, "#, &pointer),
+            Err(Error::UnexpectedEOF)
+        );
+    }
+}
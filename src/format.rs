@@ -4,26 +4,133 @@ use std::fmt::{Error, Write};
 
 use crate::{
     ast::{ArrayValue, Comment, Metadata, ObjectValue, Root, Value, ValueToken},
-    scanner::{ScanResult, Token},
+    doc::{advance, Doc, Printer},
+    escape::{pieces, write_escaped, Escaped, Piece},
+    scanner::{LineEnding, ScanResult, Token},
+    sort::sort_keys,
     validate::ValidateIter,
 };
 
+pub use crate::sort::KeyOrder;
+
 /// Options represents the customizations that can be made when formatting.
+/// Options that affect the contents of strings also apply when writing compact
+/// JSON.
 #[derive(Debug, Copy, Clone)]
 pub struct Options<'a> {
     indent: &'a str,
+    tab_width: usize,
     line_length: usize,
     max_object_pairs_per_line: usize,
     max_array_values_per_line: usize,
+    max_line_depth: usize,
+    fill_numbers: bool,
+    line_ending: LineEndingStyle,
+    lone_surrogates: LoneSurrogates,
+    string_escapes: StringEscapes,
+    sort_keys: Option<KeyOrder<'a>>,
+    trailing_commas: TrailingCommas,
+    preserve_expanded: bool,
+    align_values: bool,
+    align_comments: Option<usize>,
+    reindent_comments: bool,
+    reflow_comments: bool,
+    comment_style: CommentStyle,
+    comment_spacing: bool,
+}
+
+/// TrailingCommas determines when a comma is written after the last value in
+/// an object or array.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrailingCommas {
+    /// Never write trailing commas.
+    Never,
+    /// Write trailing commas only when the object or array spans multiple
+    /// lines.
+    Multiline,
+    /// Always write trailing commas, except in empty objects and arrays.
+    Always,
+}
+
+/// LineEndingStyle determines the line ending that is written after each line.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineEndingStyle {
+    /// Lines end with `\n`.
+    Lf,
+    /// Lines end with `\r\n`.
+    Crlf,
+    /// Lines end with the convention used most often in the source. See
+    /// [Root::line_ending].
+    Auto,
+}
+
+/// LoneSurrogates determines how `\u` escapes of UTF-16 surrogates that are
+/// not part of a valid pair, such as `"\uD800"`, are written. These are
+/// rejected by strict JSON parsers, and by a Scanner with
+/// [Scanner::with_reject_lone_surrogates](crate::scanner::Scanner::with_reject_lone_surrogates).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoneSurrogates {
+    /// Lone surrogates are written unchanged.
+    Preserve,
+    /// Lone surrogates are replaced with `\uFFFD`, or with the replacement
+    /// character itself when using `StringEscapes::Minimal`.
+    Replace,
+}
+
+/// StringEscapes determines how the contents of strings are escaped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StringEscapes {
+    /// Strings are written as they appear in the source.
+    Preserve,
+    /// Only quotes, backslashes, and control characters are escaped, using
+    /// the short escapes (such as `\n`) where they exist. All other escapes
+    /// are replaced by the characters they represent, so `"\u0041\/"` is
+    /// written as `"A/"`.
+    Minimal,
+    /// Like `StringEscapes::Minimal`, but non-ASCII characters are also
+    /// escaped, as surrogate pairs if they're outside of the Basic
+    /// Multilingual Plane.
+    Ascii,
+}
+
+/// CommentStyle determines whether comments are converted between block
+/// (`/* */`) and line (`//`) comments.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// Comments are written in their original style.
+    Preserve,
+    /// Block comments are written as line comments, one for each line of
+    /// the block comment, with any ` * ` leaders removed. Block comments
+    /// that share a line with a value are only converted if they're the last
+    /// comment on the line and span a single line.
+    Line,
+    /// Line comments are written as block comments, unless they contain
+    /// `*/` or start with another `/`, such as `///`.
+    Block,
 }
 
 impl Default for Options<'_> {
     fn default() -> Self {
         Self {
             indent: "  ",
+            tab_width: 4,
             line_length: 80,
             max_object_pairs_per_line: 1,
             max_array_values_per_line: 4,
+            max_line_depth: 0,
+            fill_numbers: false,
+            line_ending: LineEndingStyle::Lf,
+            lone_surrogates: LoneSurrogates::Preserve,
+            string_escapes: StringEscapes::Preserve,
+            sort_keys: None,
+            trailing_commas: TrailingCommas::Never,
+            preserve_expanded: false,
+            align_values: false,
+            align_comments: None,
+            reindent_comments: false,
+            reflow_comments: false,
+            comment_style: CommentStyle::Preserve,
+            comment_spacing: false,
         }
     }
 }
@@ -34,8 +141,19 @@ impl<'a> Options<'a> {
         Self { indent: s, ..self }
     }
 
-    /// Sets the line length that objects and arrays will wrap on. The default
-    /// is 80 characters.
+    /// Sets the number of columns between tab stops, used to measure tabs in
+    /// the indent and in comments. The default is 4.
+    pub fn with_tab_width(self, n: usize) -> Self {
+        Self {
+            tab_width: n,
+            ..self
+        }
+    }
+
+    /// Sets the line length that objects and arrays will wrap on. Lines are
+    /// measured by their display width, so wide characters, such as CJK
+    /// ideographs and most emoji, count as two columns, and combining marks
+    /// count as none. The default is 80 columns.
     pub fn with_line_length(self, n: usize) -> Self {
         Self {
             line_length: n,
@@ -60,6 +178,165 @@ impl<'a> Options<'a> {
             ..self
         }
     }
+
+    /// Sets how deeply objects and arrays can be nested inside of an object or
+    /// array that is written on a single line. For example, with a depth of
+    /// 1, `[[1, 2], [3, 4]]` can be written on one line, but `[[[1]]]` can't.
+    /// The default is 0.
+    pub fn with_max_line_depth(self, n: usize) -> Self {
+        Self {
+            max_line_depth: n,
+            ..self
+        }
+    }
+
+    /// Sets whether arrays of numbers that don't fit on a single line are
+    /// filled, writing as many values on each line as fit within the line
+    /// length, instead of one value per line. Arrays with comments aren't
+    /// filled. The default is false.
+    pub fn with_fill_numbers(self, fill_numbers: bool) -> Self {
+        Self {
+            fill_numbers,
+            ..self
+        }
+    }
+
+    /// Sets the line ending written after each line, including those inside of
+    /// multi-line block comments. The default is `LineEndingStyle::Lf`.
+    pub fn with_line_ending(self, line_ending: LineEndingStyle) -> Self {
+        Self {
+            line_ending,
+            ..self
+        }
+    }
+
+    /// Sets how lone surrogate escapes in strings are written.
+    /// `LoneSurrogates::Replace` replaces them with U+FFFD, so that the output
+    /// is accepted by strict JSON parsers. The default is
+    /// `LoneSurrogates::Preserve`.
+    pub fn with_lone_surrogates(self, lone_surrogates: LoneSurrogates) -> Self {
+        Self {
+            lone_surrogates,
+            ..self
+        }
+    }
+
+    /// Sets how the contents of strings are escaped. Lone surrogate escapes
+    /// are written according to [Options::with_lone_surrogates], since they
+    /// can't be written as characters. The default is
+    /// `StringEscapes::Preserve`.
+    pub fn with_string_escapes(self, string_escapes: StringEscapes) -> Self {
+        Self {
+            string_escapes,
+            ..self
+        }
+    }
+
+    /// Sets the order that object keys are sorted in, keeping comments with
+    /// their members (see [sort_keys](crate::sort::sort_keys)). This doesn't
+    /// apply to [write_json_compact_iter_opts]. By default, keys are written in
+    /// their original order.
+    pub fn with_sort_keys(self, order: KeyOrder<'a>) -> Self {
+        Self {
+            sort_keys: Some(order),
+            ..self
+        }
+    }
+
+    /// Sets when a trailing comma is written after the last value in an
+    /// object or array. The comma is written before any same-line comments.
+    /// The default is `TrailingCommas::Never`.
+    pub fn with_trailing_commas(self, trailing_commas: TrailingCommas) -> Self {
+        Self {
+            trailing_commas,
+            ..self
+        }
+    }
+
+    /// Sets whether objects and arrays that were expanded in the source stay
+    /// expanded, even if they would fit on one line. An object or array is
+    /// expanded by a newline after its opening bracket or by a trailing comma
+    /// (see [Value::expanded](crate::ast::Value::expanded)). When enabled,
+    /// `TrailingCommas::Always` doesn't write trailing commas on a single line,
+    /// since they would expand the value when formatted again. The default is
+    /// false.
+    pub fn with_preserve_expanded(self, preserve_expanded: bool) -> Self {
+        Self {
+            preserve_expanded,
+            ..self
+        }
+    }
+
+    /// Sets whether the values of object members that are written on separate
+    /// lines are aligned into a column. Members are aligned within groups that
+    /// are separated by blank lines. If aligning a member would push a value
+    /// past the line length, the column is narrowed, and members with keys
+    /// that are longer than the column aren't aligned. The default is false.
+    pub fn with_align_values(self, align_values: bool) -> Self {
+        Self {
+            align_values,
+            ..self
+        }
+    }
+
+    /// Aligns the same-line comments of consecutive object members or array
+    /// values into a column, which is at most `max_column` characters from the
+    /// start of the line. Comments that can't be aligned within `max_column`
+    /// are written one space after their value. A blank line, a comment on its
+    /// own line, or a value that spans multiple lines ends the run of aligned
+    /// comments. By default, comments aren't aligned.
+    pub fn with_align_comments(self, max_column: usize) -> Self {
+        Self {
+            align_comments: Some(max_column),
+            ..self
+        }
+    }
+
+    /// Sets whether the lines after the first line of block comments on their
+    /// own line are re-indented to the indentation of the comment. If every
+    /// line starts with a `*` leader, the leaders are aligned one column after
+    /// the start of the comment; otherwise the lines keep their indentation
+    /// relative to each other, starting after the `/* `. Block comments that
+    /// follow a value are written as they are. The default is false.
+    pub fn with_reindent_comments(self, reindent_comments: bool) -> Self {
+        Self {
+            reindent_comments,
+            ..self
+        }
+    }
+
+    /// Sets whether paragraphs of `//` comments on their own lines are
+    /// reflowed when one of their lines is longer than the line length. A
+    /// paragraph ends at a blank line, an empty comment, or a comment that
+    /// doesn't start with a single space, such as indented code or a list
+    /// item. The default is false.
+    pub fn with_reflow_comments(self, reflow_comments: bool) -> Self {
+        Self {
+            reflow_comments,
+            ..self
+        }
+    }
+
+    /// Sets the style that comments are converted to. Comments aren't
+    /// reflowed when they're converted to `CommentStyle::Block`. The default
+    /// is `CommentStyle::Preserve`.
+    pub fn with_comment_style(self, comment_style: CommentStyle) -> Self {
+        Self {
+            comment_style,
+            ..self
+        }
+    }
+
+    /// Sets whether the text of `//` comments starts with exactly one space,
+    /// and empty `//` comments have no trailing whitespace. Comments that
+    /// start with another `/`, such as `///`, are left as they are. The
+    /// default is false.
+    pub fn with_normalize_comment_spacing(self, comment_spacing: bool) -> Self {
+        Self {
+            comment_spacing,
+            ..self
+        }
+    }
 }
 
 /// Serializes/formats the provided JSON [Root] value to the writer as "jsonc".
@@ -75,328 +352,771 @@ pub fn write_jsonc<W: Write>(w: &mut W, root: &Root) -> Result<(), Error> {
 ///
 /// The output written to `w` is intended for human viewing.
 pub fn write_jsonc_opts<W: Write>(w: &mut W, root: &Root, opts: &Options) -> Result<(), Error> {
-    let mut ctx = Context {
+    let sorted;
+    let root = match opts.sort_keys {
+        Some(order) => {
+            sorted = sorted_root(root, order);
+            &sorted
+        }
+        None => root,
+    };
+    let newline = match opts.line_ending {
+        LineEndingStyle::Lf => LineEnding::Lf,
+        LineEndingStyle::Crlf => LineEnding::Crlf,
+        LineEndingStyle::Auto => root.line_ending(),
+    };
+    let ctx = Context { opts: *opts };
+    let mut docs = ctx.metadata_lines(&root.meta_above);
+    docs.push(ctx.value_doc(&root.value, 0, false));
+    docs.push(ctx.comments_doc(&root.value.comments));
+    docs.extend(ctx.metadata_lines(&root.meta_below));
+    docs.push(Doc::HardLine);
+
+    let mut printer = Printer {
         w,
-        current_line_chars: 0,
-        opts: *opts,
+        line_length: opts.line_length,
+        indent: opts.indent,
+        tab_width: opts.tab_width,
+        newline: if newline == LineEnding::Crlf {
+            "\r\n"
+        } else {
+            "\n"
+        },
     };
-    for meta in &root.meta_above {
-        ctx.write_metadata(meta)?;
-        ctx.write_newline()?;
-    }
-    ctx.write_value(&root.value.token, 0, false)?;
-    ctx.write_comments(&root.value.comments)?;
-    for meta in &root.meta_below {
-        ctx.write_metadata(meta)?;
-        ctx.write_newline()?;
-    }
-    ctx.write_newline()
+    printer.print(&Doc::Concat(docs))
 }
 
-struct Context<'a, W: Write> {
-    w: &'a mut W,
-    current_line_chars: usize,
-    opts: Options<'a>,
+/// Context builds the [Doc] that a `Root` is printed from.
+struct Context<'o> {
+    opts: Options<'o>,
 }
 
-impl<'a, W: Write> Context<'a, W> {
-    fn write_value(
-        &mut self,
-        value: &ValueToken,
-        indent: usize,
-        allow_sameline: bool,
-    ) -> Result<(), Error> {
-        match value {
-            ValueToken::Object(vals) => self.write_json_object(vals, indent, allow_sameline),
-            ValueToken::Array(vals) => self.write_json_array(vals, indent, allow_sameline),
-            ValueToken::String(v) => self.write_json_string(v),
-            ValueToken::Number(v) => self.write_str(v),
-            ValueToken::Bool(v) => self.write_json_bool(*v),
-            ValueToken::Null => self.write_str("null"),
+impl Context<'_> {
+    /// Returns the document for a value. Objects and arrays that are allowed
+    /// on the same line are grouped, so that they're only broken into
+    /// multiple lines if they don't fit.
+    fn value_doc<'a>(&self, value: &Value<'a>, indent: usize, allow_sameline: bool) -> Doc<'a> {
+        let same_line = allow_sameline && self.can_flatten(value, self.opts.max_line_depth);
+        match &value.token {
+            ValueToken::Object(vals) => self.object_doc(vals, indent, same_line),
+            ValueToken::Array(vals) => self.array_doc(vals, indent, same_line),
+            ValueToken::String(v) => self.string_doc(v),
+            ValueToken::Number(v) => Doc::text(*v),
+            ValueToken::Bool(v) => Doc::text(if *v { "true" } else { "false" }),
+            ValueToken::Null => Doc::text("null"),
         }
     }
 
-    fn write_json_object(
-        &mut self,
-        vals: &[ObjectValue],
-        indent: usize,
-        allow_sameline: bool,
-    ) -> Result<(), Error> {
-        let length = vals.len();
-        let same_line = allow_sameline
-            && self.opts.line_length > self.current_line_chars()
-            && self
-                .can_fit_object(vals, self.opts.line_length - self.current_line_chars())
-                .is_some();
-
-        self.write_char('{')?;
-        for (i, val) in vals.iter().enumerate() {
-            if same_line {
-                self.write_char(' ')?;
-            } else {
-                self.write_newline()?;
-                match val {
-                    ObjectValue::Metadata(Metadata::Newline) => {}
-                    _ => self.write_indent(indent + 1)?,
+    fn object_doc<'a>(&self, vals: &[ObjectValue<'a>], indent: usize, same_line: bool) -> Doc<'a> {
+        if vals.is_empty() {
+            return Doc::text("{}");
+        }
+        // Metadata after the last member isn't followed by a comma.
+        let last = vals
+            .iter()
+            .rposition(|val| matches!(val, ObjectValue::KeyVal(..)))
+            .unwrap_or(0);
+        let mut docs: Vec<_> = vals
+            .iter()
+            .map(|val| match val {
+                ObjectValue::KeyVal(_, v) => self.value_doc(v, indent + 1, true),
+                ObjectValue::Metadata(meta) => self.metadata_doc(meta),
+            })
+            .collect();
+        // The keys as they're written, which may differ in width from the
+        // source when strings are escaped.
+        let keys: Vec<_> = vals
+            .iter()
+            .map(|val| match val {
+                ObjectValue::KeyVal(k, _) => self.string_doc(k),
+                ObjectValue::Metadata(_) => Doc::text(""),
+            })
+            .collect();
+        let key_widths: Vec<_> = keys
+            .iter()
+            .map(|key| key.flat_width(self.opts.tab_width).unwrap_or(0))
+            .collect();
+        let padding = self.value_padding(vals, &key_widths, &docs, indent + 1);
+
+        if same_line {
+            let mut members = vec![Doc::Line];
+            for (i, ((val, doc), key)) in vals.iter().zip(docs).zip(keys).enumerate() {
+                if let ObjectValue::KeyVal(..) = val {
+                    if i > 0 {
+                        members.extend([Doc::text(","), Doc::Line]);
+                    }
+                    members.extend([
+                        key,
+                        Doc::text(": "),
+                        Doc::if_break(Doc::text(" ".repeat(padding[i]))),
+                        doc,
+                    ]);
                 }
             }
+            members.push(self.trailing_comma_doc());
+            return Doc::group(Doc::Concat(vec![
+                Doc::text("{"),
+                Doc::indent(Doc::Concat(members)),
+                Doc::Line,
+                Doc::text("}"),
+            ]));
+        }
+
+        let indent_width = self.indent_width(indent + 1);
+        let lines: Vec<_> = vals
+            .iter()
+            .zip(&docs)
+            .enumerate()
+            .map(|(i, (val, doc))| match val {
+                ObjectValue::KeyVal(_, v) => {
+                    let prefix = indent_width + key_widths[i] + 2 + padding[i];
+                    let comma = i < last || self.trailing_comma(false);
+                    self.line_width(v, doc, prefix, comma)
+                        .map(|width| (width, !v.comments.is_empty()))
+                }
+                ObjectValue::Metadata(_) => None,
+            })
+            .collect();
+        let columns = self.comment_columns(&lines);
+
+        let metas: Vec<_> = vals
+            .iter()
+            .map(|val| match val {
+                ObjectValue::Metadata(meta) => Some(meta),
+                ObjectValue::KeyVal(..) => None,
+            })
+            .collect();
+        let merged = self.reflow_comments(&metas, &mut docs, indent + 1);
+
+        let mut members = Vec::new();
+        for (i, ((val, doc), key)) in vals.iter().zip(docs).zip(keys).enumerate() {
+            if merged[i] {
+                continue;
+            }
+            members.push(Doc::HardLine);
             match val {
-                ObjectValue::KeyVal(k, v) => {
-                    self.write_json_string(k)?;
-                    self.write_str(": ")?;
-                    self.write_value(&v.token, indent + 1, true)?;
-                    if i < length - 1 {
-                        self.write_char(',')?;
-                    }
-                    self.write_comments(&v.comments)?;
+                ObjectValue::KeyVal(_, v) => {
+                    let comma = i < last || self.trailing_comma(false);
+                    members.extend([
+                        key,
+                        Doc::text(": "),
+                        Doc::text(" ".repeat(padding[i])),
+                        doc,
+                        Doc::text(if comma { "," } else { "" }),
+                        self.comment_padding(lines[i], columns[i]),
+                        self.comments_doc(&v.comments),
+                    ]);
                 }
-                ObjectValue::Metadata(meta) => self.write_metadata(meta)?,
+                ObjectValue::Metadata(_) => members.push(doc),
             }
         }
-        if length > 0 {
-            if same_line {
-                self.write_char(' ')?;
-            } else {
-                self.write_newline()?;
-                self.write_indent(indent)?;
+        Doc::Concat(vec![
+            Doc::text("{"),
+            Doc::indent(Doc::Concat(members)),
+            Doc::HardLine,
+            Doc::text("}"),
+        ])
+    }
+
+    fn array_doc<'a>(&self, vals: &[ArrayValue<'a>], indent: usize, same_line: bool) -> Doc<'a> {
+        if vals.is_empty() {
+            return Doc::text("[]");
+        }
+        if self.opts.fill_numbers {
+            if let Some(numbers) = numbers(vals) {
+                return self.filled_array_doc(&numbers, same_line);
             }
         }
-        self.write_char('}')
-    }
+        let last = vals
+            .iter()
+            .rposition(|val| matches!(val, ArrayValue::ArrayVal(_)))
+            .unwrap_or(0);
+        let mut docs: Vec<_> = vals
+            .iter()
+            .map(|val| match val {
+                ArrayValue::ArrayVal(v) => self.value_doc(v, indent + 1, true),
+                ArrayValue::Metadata(meta) => self.metadata_doc(meta),
+            })
+            .collect();
 
-    fn write_json_array(
-        &mut self,
-        vals: &[ArrayValue],
-        indent: usize,
-        allow_sameline: bool,
-    ) -> Result<(), Error> {
-        let length = vals.len();
-        let same_line = allow_sameline
-            && self.opts.line_length > self.current_line_chars()
-            && self
-                .can_fit_array(vals, self.opts.line_length - self.current_line_chars())
-                .is_some();
-
-        self.write_char('[')?;
-        for (i, val) in vals.iter().enumerate() {
-            if same_line {
+        if same_line {
+            let mut values = vec![Doc::SoftLine];
+            for (i, doc) in docs.into_iter().enumerate() {
                 if i > 0 {
-                    self.write_char(' ')?;
-                }
-            } else {
-                self.write_newline()?;
-                match val {
-                    ArrayValue::Metadata(Metadata::Newline) => {}
-                    _ => self.write_indent(indent + 1)?,
+                    values.extend([Doc::text(","), Doc::Line]);
                 }
+                values.push(doc);
             }
-            match val {
+            values.push(self.trailing_comma_doc());
+            return Doc::group(Doc::Concat(vec![
+                Doc::text("["),
+                Doc::indent(Doc::Concat(values)),
+                Doc::SoftLine,
+                Doc::text("]"),
+            ]));
+        }
+
+        let indent_width = self.indent_width(indent + 1);
+        let lines: Vec<_> = vals
+            .iter()
+            .zip(&docs)
+            .enumerate()
+            .map(|(i, (val, doc))| match val {
                 ArrayValue::ArrayVal(v) => {
-                    self.write_value(&v.token, indent + 1, true)?;
-                    if i < length - 1 {
-                        self.write_char(',')?;
-                    }
-                    self.write_comments(&v.comments)?;
+                    let comma = i < last || self.trailing_comma(false);
+                    self.line_width(v, doc, indent_width, comma)
+                        .map(|width| (width, !v.comments.is_empty()))
                 }
-                ArrayValue::Metadata(meta) => self.write_metadata(meta)?,
+                ArrayValue::Metadata(_) => None,
+            })
+            .collect();
+        let columns = self.comment_columns(&lines);
+
+        let metas: Vec<_> = vals
+            .iter()
+            .map(|val| match val {
+                ArrayValue::Metadata(meta) => Some(meta),
+                ArrayValue::ArrayVal(_) => None,
+            })
+            .collect();
+        let merged = self.reflow_comments(&metas, &mut docs, indent + 1);
+
+        let mut values = Vec::new();
+        for (i, (val, doc)) in vals.iter().zip(docs).enumerate() {
+            if merged[i] {
+                continue;
+            }
+            values.extend([Doc::HardLine, doc]);
+            if let ArrayValue::ArrayVal(v) = val {
+                let comma = i < last || self.trailing_comma(false);
+                values.extend([
+                    Doc::text(if comma { "," } else { "" }),
+                    self.comment_padding(lines[i], columns[i]),
+                    self.comments_doc(&v.comments),
+                ]);
             }
         }
-        if length > 0 && !same_line {
-            self.write_newline()?;
-            self.write_indent(indent)?;
-        }
-        self.write_char(']')
+        Doc::Concat(vec![
+            Doc::text("["),
+            Doc::indent(Doc::Concat(values)),
+            Doc::HardLine,
+            Doc::text("]"),
+        ])
     }
 
-    fn write_json_bool(&mut self, v: bool) -> Result<(), Error> {
-        if v {
-            self.write_str("true")
+    /// Returns the document for an array of numbers that are written on as
+    /// few lines as possible. See [Options::with_fill_numbers].
+    fn filled_array_doc<'a>(&self, numbers: &[&'a str], same_line: bool) -> Doc<'a> {
+        let (line, trailing_comma) = if same_line {
+            (Doc::SoftLine, self.trailing_comma_doc())
+        } else {
+            let comma = self.trailing_comma(false);
+            (Doc::HardLine, Doc::text(if comma { "," } else { "" }))
+        };
+        // The trailing comma is part of the last value, so that it's counted
+        // when filling the last line.
+        let mut parts = Vec::new();
+        let (last, numbers) = numbers.split_last().expect("filled arrays aren't empty");
+        for number in numbers {
+            parts.extend([
+                Doc::Concat(vec![Doc::text(*number), Doc::text(",")]),
+                Doc::Line,
+            ]);
+        }
+        parts.push(Doc::Concat(vec![Doc::text(*last), trailing_comma]));
+        let doc = Doc::Concat(vec![
+            Doc::text("["),
+            Doc::indent(Doc::Concat(vec![line.clone(), Doc::Fill(parts)])),
+            line,
+            Doc::text("]"),
+        ]);
+        if same_line {
+            Doc::group(doc)
         } else {
-            self.write_str("false")
+            doc
         }
     }
 
-    fn current_line_chars(&self) -> usize {
-        self.current_line_chars
+    /// Returns whether the value can be written on a single line, ignoring
+    /// its width. Objects and arrays can contain other objects and arrays
+    /// nested up to `depth` levels deep.
+    fn can_flatten(&self, value: &Value, depth: usize) -> bool {
+        let can_flatten_child = |v: &Value| {
+            v.comments.is_empty()
+                && match v.token {
+                    ValueToken::Object(_) | ValueToken::Array(_) => {
+                        depth > 0 && self.can_flatten(v, depth - 1)
+                    }
+                    _ => true,
+                }
+        };
+        if self.opts.preserve_expanded && value.expanded {
+            return false;
+        }
+        match &value.token {
+            ValueToken::Object(vals) => {
+                vals.len() <= self.opts.max_object_pairs_per_line
+                    && vals.iter().all(|val| match val {
+                        ObjectValue::KeyVal(_, v) => can_flatten_child(v),
+                        ObjectValue::Metadata(_) => false,
+                    })
+            }
+            ValueToken::Array(vals) => {
+                vals.len() <= self.opts.max_array_values_per_line
+                    && vals.iter().all(|val| match val {
+                        ArrayValue::ArrayVal(v) => can_flatten_child(v),
+                        ArrayValue::Metadata(_) => false,
+                    })
+            }
+            _ => true,
+        }
     }
 
-    fn write_metadata(&mut self, meta: &Metadata) -> Result<(), Error> {
-        if let Metadata::Comment(c) = meta {
-            self.write_comment(c)?;
+    /// Returns the number of spaces to write after the colon of each member
+    /// to align their values, given the widths of the keys as they're
+    /// written. See [Options::with_align_values].
+    fn value_padding(
+        &self,
+        vals: &[ObjectValue],
+        key_widths: &[usize],
+        docs: &[Doc],
+        indent: usize,
+    ) -> Vec<usize> {
+        let mut padding = vec![0; vals.len()];
+        if !self.opts.align_values {
+            return padding;
         }
-        Ok(())
+        let indent_width = self.indent_width(indent);
+        let mut start = 0;
+        for end in 0..=vals.len() {
+            if end < vals.len() && vals[end] != ObjectValue::Metadata(Metadata::Newline) {
+                continue;
+            }
+            // The width of each key, including its quotes and colon, and of
+            // the value and comma after it.
+            let widths: Vec<_> = vals[start..end]
+                .iter()
+                .zip(&key_widths[start..end])
+                .zip(&docs[start..end])
+                .map(|((val, key), doc)| match val {
+                    ObjectValue::KeyVal(..) => {
+                        // Values that don't fit on a line by themselves, such
+                        // as multi-line containers, only need room to start.
+                        let value = match doc.flat_width(self.opts.tab_width) {
+                            Some(width) if width <= self.opts.line_length => width,
+                            _ => 1,
+                        };
+                        Some((key + 1, value + 1))
+                    }
+                    ObjectValue::Metadata(_) => None,
+                })
+                .collect();
+            let mut columns: Vec<_> = widths.iter().flatten().map(|(k, _)| *k).collect();
+            columns.sort_unstable();
+            columns.dedup();
+            // Use the widest column that keeps every aligned value within the
+            // line length.
+            let column = columns.into_iter().rev().find(|&column| {
+                widths.iter().flatten().all(|&(key, value)| {
+                    key > column || indent_width + column + 1 + value <= self.opts.line_length
+                })
+            });
+            if let Some(column) = column {
+                for (pad, width) in padding[start..end].iter_mut().zip(&widths) {
+                    if let Some((key, _)) = width {
+                        *pad = column.saturating_sub(*key);
+                    }
+                }
+            }
+            start = end + 1;
+        }
+        padding
     }
 
-    fn write_comments(&mut self, cs: &[Comment]) -> Result<(), Error> {
-        for comment in cs {
-            self.write_char(' ')?;
-            self.write_comment(comment)?;
+    /// Returns the width of the line that a member or array value is written
+    /// on, or `None` if the value spans multiple lines. `prefix` is the width
+    /// of the line before the value.
+    fn line_width(&self, value: &Value, doc: &Doc, prefix: usize, comma: bool) -> Option<usize> {
+        let width = prefix + doc.flat_width(self.opts.tab_width)? + comma as usize;
+        match value.token {
+            ValueToken::Object(_) | ValueToken::Array(_) if width > self.opts.line_length => None,
+            _ => Some(width),
         }
-        Ok(())
     }
 
-    fn write_comment(&mut self, comment: &Comment) -> Result<(), Error> {
-        match comment {
-            Comment::Block(c) => {
-                self.write_str("/*")?;
-                self.write_str(c)?;
-                if let Some(i) = c.rfind('\n') {
-                    // If the block comment contains newlines, adjust the
-                    // internal value of chars written for the current line.
-                    self.current_line_chars = c[(i + 1)..].chars().count();
+    /// Returns the column to align the same-line comments of each line to,
+    /// given the width of each line and whether it has comments. See
+    /// [Options::with_align_comments].
+    fn comment_columns(&self, lines: &[Option<(usize, bool)>]) -> Vec<Option<usize>> {
+        let mut columns = vec![None; lines.len()];
+        let Some(max_column) = self.opts.align_comments else {
+            return columns;
+        };
+        for (start, run) in runs(lines) {
+            let column = run
+                .iter()
+                .filter(|(_, comments)| *comments)
+                .map(|(width, _)| width + 1)
+                .filter(|&column| column <= max_column)
+                .max();
+            for (i, (width, comments)) in run.iter().enumerate() {
+                if *comments && column.is_some_and(|column| *width < column) {
+                    columns[start + i] = column;
                 }
-                self.write_str("*/")
-            }
-            Comment::Line(c) => {
-                self.write_str("//")?;
-                self.write_str(c)
             }
         }
+        columns
     }
 
-    fn write_json_string(&mut self, s: &str) -> Result<(), Error> {
-        self.write_char('"')?;
-        self.write_str(s)?;
-        self.write_char('"')
+    /// Returns the spaces that move the same-line comments of a line of the
+    /// given width to the column.
+    fn comment_padding(&self, line: Option<(usize, bool)>, column: Option<usize>) -> Doc<'static> {
+        match (line, column) {
+            (Some((width, _)), Some(column)) => Doc::text(" ".repeat(column - 1 - width)),
+            _ => Doc::text(""),
+        }
     }
 
-    fn write_indent(&mut self, n: usize) -> Result<(), Error> {
-        for _ in 0..n {
-            self.write_str(self.opts.indent)?;
+    fn trailing_comma(&self, same_line: bool) -> bool {
+        match self.opts.trailing_commas {
+            TrailingCommas::Never => false,
+            TrailingCommas::Multiline => !same_line,
+            TrailingCommas::Always => !same_line || !self.opts.preserve_expanded,
         }
-        Ok(())
     }
 
-    fn write_str(&mut self, s: &str) -> Result<(), Error> {
-        self.w.write_str(s)?;
-        self.current_line_chars += s.chars().count();
-        Ok(())
+    /// Returns the trailing comma of a grouped object or array, which depends
+    /// on whether the group is broken.
+    fn trailing_comma_doc(&self) -> Doc<'static> {
+        match (self.trailing_comma(true), self.trailing_comma(false)) {
+            (true, _) => Doc::text(","),
+            (false, true) => Doc::if_break(Doc::text(",")),
+            (false, false) => Doc::text(""),
+        }
     }
 
-    fn write_newline(&mut self) -> Result<(), Error> {
-        self.write_char('\n')?;
-        self.current_line_chars = 0;
-        Ok(())
+    /// Returns the display width of the string, as if written at the start of
+    /// a line.
+    fn width(&self, s: &str) -> usize {
+        advance(0, s, self.opts.tab_width)
     }
 
-    fn write_char(&mut self, c: char) -> Result<(), Error> {
-        self.w.write_char(c)?;
-        self.current_line_chars += 1;
-        Ok(())
+    /// Returns the display width of `n` levels of indentation.
+    fn indent_width(&self, n: usize) -> usize {
+        (0..n).fold(0, |column, _| {
+            advance(column, self.opts.indent, self.opts.tab_width)
+        })
     }
 
-    fn can_fit_value(&self, val: &ValueToken, space: usize) -> Option<usize> {
-        let remaining = space as i64;
-        let remaining = match val {
-            ValueToken::Object(v) => return self.can_fit_object(v, space),
-            ValueToken::Array(v) => return self.can_fit_array(v, space),
-            ValueToken::String(v) => remaining - (2 + v.chars().count() as i64),
-            ValueToken::Number(v) => remaining - v.len() as i64,
-            ValueToken::Bool(v) => {
-                if *v {
-                    remaining - 4
-                } else {
-                    remaining - 5
+    /// Returns the documents for metadata outside of the root value, each
+    /// followed by a newline.
+    fn metadata_lines<'a>(&self, metas: &[Metadata<'a>]) -> Vec<Doc<'a>> {
+        let mut docs: Vec<_> = metas.iter().map(|meta| self.metadata_doc(meta)).collect();
+        let merged =
+            self.reflow_comments(&metas.iter().map(Some).collect::<Vec<_>>(), &mut docs, 0);
+        docs.into_iter()
+            .zip(merged)
+            .filter(|(_, merged)| !merged)
+            .flat_map(|(doc, _)| [doc, Doc::HardLine])
+            .collect()
+    }
+
+    /// Reflows the paragraphs of line comments in the metadata, replacing
+    /// the document of the first comment of each reflowed paragraph. Returns
+    /// whether each of the metadata was merged into an earlier comment. See
+    /// [Options::with_reflow_comments].
+    fn reflow_comments<'a>(
+        &self,
+        metas: &[Option<&Metadata<'a>>],
+        docs: &mut [Doc<'a>],
+        indent: usize,
+    ) -> Vec<bool> {
+        let mut merged = vec![false; metas.len()];
+        if !self.opts.reflow_comments || self.opts.comment_style == CommentStyle::Block {
+            return merged;
+        }
+        let max_width = self
+            .opts
+            .line_length
+            .saturating_sub(self.indent_width(indent) + 2);
+        let mut start = 0;
+        while start < metas.len() {
+            let paragraph: Vec<_> = metas[start..]
+                .iter()
+                .map_while(|meta| match meta {
+                    Some(Metadata::Comment(Comment::Line(c))) if is_prose(c) => Some(*c),
+                    _ => None,
+                })
+                .collect();
+            if paragraph.iter().any(|c| self.width(c) > max_width) {
+                let mut parts = Vec::new();
+                for (i, word) in paragraph
+                    .iter()
+                    .flat_map(|c| c.split_whitespace())
+                    .enumerate()
+                {
+                    if i > 0 {
+                        parts.push(Doc::Concat(vec![
+                            Doc::Line,
+                            Doc::if_break(Doc::text("// ")),
+                        ]));
+                    }
+                    parts.push(Doc::text(word));
                 }
+                docs[start] = Doc::Concat(vec![Doc::text("// "), Doc::Fill(parts)]);
+                merged[start + 1..start + paragraph.len()].fill(true);
             }
-            ValueToken::Null => remaining - 4,
-        };
-        if remaining >= 0 {
-            Some(remaining as usize)
-        } else {
-            None
+            start += paragraph.len().max(1);
         }
+        merged
     }
 
-    fn can_fit_object(&self, vals: &[ObjectValue], space: usize) -> Option<usize> {
-        let num_vals = vals.len();
-        if num_vals > self.opts.max_object_pairs_per_line {
-            return None;
+    fn metadata_doc<'a>(&self, meta: &Metadata<'a>) -> Doc<'a> {
+        match meta {
+            Metadata::Comment(c) => self.comment_doc(c, true, true),
+            Metadata::Newline => Doc::text(""),
         }
+    }
 
-        let mut remaining = (space as i64) - 2; // For object start/close.
-        if !vals.is_empty() {
-            // Object padding + (key quotes + colon + padding) * values + (comma + padding) * (values - 1).
-            remaining -= 2 + 4 * num_vals as i64 + 2 * (num_vals as i64 - 1);
+    /// Returns the same-line comments of a value, which aren't counted when
+    /// deciding whether a group fits.
+    fn comments_doc<'a>(&self, cs: &[Comment<'a>]) -> Doc<'a> {
+        let mut docs = Vec::new();
+        for (i, comment) in cs.iter().enumerate() {
+            docs.extend([
+                Doc::text(" "),
+                self.comment_doc(comment, false, i == cs.len() - 1),
+            ]);
         }
-        if remaining < 0 {
-            return None;
+        Doc::suffix(Doc::Concat(docs))
+    }
+
+    /// Returns the document for a comment, converted to the comment style
+    /// where that's safe. `own_line` is whether the comment is on its own
+    /// line, and `ends_line` is whether it's the last thing on its line.
+    fn comment_doc<'a>(&self, comment: &Comment<'a>, own_line: bool, ends_line: bool) -> Doc<'a> {
+        match (comment, self.opts.comment_style) {
+            (Comment::Block(c), CommentStyle::Line)
+                if ends_line && (own_line || !c.contains('\n')) =>
+            {
+                block_to_line_doc(c)
+            }
+            (Comment::Line(c), CommentStyle::Block) if !c.starts_with('/') && !c.contains("*/") => {
+                match c.trim() {
+                    "" => Doc::text("/* */"),
+                    c => Doc::text(format!("/* {c} */")),
+                }
+            }
+            _ => self.comment_text_doc(comment, own_line),
         }
-        for val in vals {
-            match val {
-                ObjectValue::Metadata(_) => return None,
-                ObjectValue::KeyVal(k, v) => {
-                    if !v.comments.is_empty() {
-                        return None;
-                    }
-                    remaining -= k.chars().count() as i64;
-                    if remaining < 0 {
-                        return None;
-                    }
-                    match v.token {
-                        ValueToken::Array(_) => return None,
-                        ValueToken::Object(_) => return None,
-                        _ => {}
-                    }
-                    match self.can_fit_value(&v.token, remaining as usize) {
-                        None => return None,
-                        Some(size) => {
-                            remaining = size as i64;
-                        }
+    }
+
+    /// Returns the document for a comment in its original style.
+    fn comment_text_doc<'a>(&self, comment: &Comment<'a>, own_line: bool) -> Doc<'a> {
+        match comment {
+            Comment::Block(c) if self.opts.reindent_comments && own_line && c.contains('\n') => {
+                reindented_block_doc(c)
+            }
+            Comment::Block(c) => {
+                let mut docs = vec![Doc::text("/*")];
+                let mut lines = c.split('\n').peekable();
+                while let Some(line) = lines.next() {
+                    if lines.peek().is_some() {
+                        docs.push(Doc::text(line.strip_suffix('\r').unwrap_or(line)));
+                        docs.push(Doc::LiteralLine);
+                    } else {
+                        docs.push(Doc::text(line));
                     }
                 }
+                docs.push(Doc::text("*/"));
+                Doc::Concat(docs)
             }
+            Comment::Line(c) if self.opts.comment_spacing && !c.starts_with('/') => {
+                match c.trim() {
+                    "" => Doc::text("//"),
+                    _ => Doc::Concat(vec![Doc::text("// "), Doc::text(c.trim_start())]),
+                }
+            }
+            Comment::Line(c) => Doc::Concat(vec![Doc::text("//"), Doc::text(*c)]),
         }
+    }
 
-        if remaining >= 0 {
-            Some(remaining as usize)
+    fn string_doc<'a>(&self, s: &str) -> Doc<'a> {
+        let mut out = String::with_capacity(s.len() + 2);
+        // Writing to a String can't fail.
+        let _ = write_json_string(&mut out, s, &self.opts);
+        Doc::text(out)
+    }
+}
+
+/// Returns the numbers in the array, or `None` if it's empty or contains
+/// anything other than numbers without comments.
+fn numbers<'a>(vals: &[ArrayValue<'a>]) -> Option<Vec<&'a str>> {
+    if vals.is_empty() {
+        return None;
+    }
+    vals.iter()
+        .map(|val| match val {
+            ArrayValue::ArrayVal(Value {
+                token: ValueToken::Number(n),
+                comments,
+                ..
+            }) if comments.is_empty() => Some(*n),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns whether a line comment is prose that can be reflowed: it starts
+/// with a single space, and its first word isn't a list marker.
+fn is_prose(c: &str) -> bool {
+    let Some(text) = c.strip_prefix(' ') else {
+        return false;
+    };
+    match text.split_whitespace().next() {
+        Some("-" | "*" | "+") | None => false,
+        Some(_) => !text.starts_with(char::is_whitespace),
+    }
+}
+
+/// Returns the document for a multi-line block comment whose lines after the
+/// first are written at the current indentation, plus the width of `/* ` for
+/// lines without leaders. See
+/// [Options::with_reindent_comments].
+fn reindented_block_doc(c: &str) -> Doc<'_> {
+    let mut lines = c
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line));
+    let mut docs = vec![Doc::text("/*")];
+    docs.extend(lines.next().map(Doc::text));
+    let rest: Vec<_> = lines.collect();
+    let leaders = rest.iter().any(|line| line.trim_start().starts_with('*'))
+        && rest.iter().all(|line| {
+            let line = line.trim_start();
+            line.is_empty() || line.starts_with('*')
+        });
+    let common = rest
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    for (i, line) in rest.iter().enumerate() {
+        docs.push(Doc::HardLine);
+        let last = i == rest.len() - 1;
+        if line.trim().is_empty() {
+            if leaders && last {
+                docs.push(Doc::text(" "));
+            }
+        } else if leaders {
+            docs.push(Doc::text(format!(" {}", line.trim_start())));
         } else {
-            None
+            docs.push(Doc::text(format!("   {}", &line[common..])));
         }
     }
+    docs.push(Doc::text("*/"));
+    Doc::Concat(docs)
+}
 
-    fn can_fit_array(&self, vals: &[ArrayValue], space: usize) -> Option<usize> {
-        let num_vals = vals.len();
-        if num_vals > self.opts.max_array_values_per_line {
-            return None;
-        }
+/// Returns the document for a block comment written as line comments, one
+/// for each line of the block comment. Leading and trailing blank lines, and
+/// ` * ` leaders, are removed.
+fn block_to_line_doc(c: &str) -> Doc<'_> {
+    let lines: Vec<_> = c.split('\n').map(str::trim).collect();
+    let leaders = lines[1..].iter().any(|line| line.starts_with('*'))
+        && lines[1..]
+            .iter()
+            .all(|line| line.is_empty() || line.starts_with('*'));
+    let mut lines: Vec<_> = lines
+        .into_iter()
+        .map(|line| match line.strip_prefix('*') {
+            Some(rest) if leaders => rest.trim_start(),
+            _ => line,
+        })
+        .collect();
+    // The first line may start with the `*`s of a `/**` comment.
+    let first = lines[0].trim_start_matches('*');
+    if first.is_empty() || first.starts_with(char::is_whitespace) {
+        lines[0] = first.trim_start();
+    }
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(0);
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(start, |i| i + 1);
 
-        let mut remaining = (space as i64) - 2; // For array start/close.
-        if !vals.is_empty() {
-            // (comma + padding) * (values - 1).
-            remaining -= 2 * (num_vals as i64 - 1);
+    let mut docs = Vec::new();
+    for (i, line) in lines[start..end.max(start + 1)].iter().enumerate() {
+        if i > 0 {
+            docs.push(Doc::HardLine);
         }
-        if remaining < 0 {
-            return None;
+        docs.push(match *line {
+            "" => Doc::text("//"),
+            line => Doc::Concat(vec![Doc::text("// "), Doc::text(line)]),
+        });
+    }
+    Doc::Concat(docs)
+}
+
+/// Returns the runs of consecutive `Some` values, with the index that each
+/// run starts at.
+fn runs<T: Copy>(items: &[Option<T>]) -> Vec<(usize, Vec<T>)> {
+    let mut runs = Vec::new();
+    let mut run: Vec<T> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        match item {
+            Some(item) => run.push(*item),
+            None if !run.is_empty() => runs.push((i - run.len(), std::mem::take(&mut run))),
+            None => {}
         }
-        for val in vals {
-            match val {
-                ArrayValue::Metadata(_) => return None,
-                ArrayValue::ArrayVal(v) => {
-                    if !v.comments.is_empty() {
-                        return None;
-                    }
-                    match v.token {
-                        ValueToken::Array(_) => return None,
-                        ValueToken::Object(_) => return None,
-                        _ => {}
-                    }
-                    match self.can_fit_value(&v.token, remaining as usize) {
-                        None => return None,
-                        Some(size) => {
-                            remaining = size as i64;
-                        }
+    }
+    if !run.is_empty() {
+        runs.push((items.len() - run.len(), run));
+    }
+    runs
+}
+
+fn sorted_root<'a>(root: &Root<'a>, order: KeyOrder) -> Root<'a> {
+    let mut root = root.clone();
+    sort_keys(&mut root, order);
+    root
+}
+
+/// Writes the contents of a JSON string, surrounded by quotes, according to the
+/// string options.
+fn write_json_string<W: Write>(w: &mut W, s: &str, opts: &Options) -> Result<(), Error> {
+    w.write_char('"')?;
+    let replace = opts.lone_surrogates == LoneSurrogates::Replace;
+    let ascii = opts.string_escapes == StringEscapes::Ascii;
+    if opts.string_escapes == StringEscapes::Preserve && !replace {
+        w.write_str(s)?;
+    } else {
+        for piece in pieces(s) {
+            match piece {
+                Piece::Escape(_, Escaped::LoneSurrogate(_))
+                    if replace && opts.string_escapes == StringEscapes::Preserve =>
+                {
+                    w.write_str("\\uFFFD")?
+                }
+                Piece::Escape(_, Escaped::LoneSurrogate(_)) if replace => {
+                    write_escaped(w, '\u{FFFD}', ascii)?
+                }
+                Piece::Literal(v) | Piece::Escape(v, _)
+                    if opts.string_escapes == StringEscapes::Preserve =>
+                {
+                    w.write_str(v)?
+                }
+                Piece::Escape(v, Escaped::LoneSurrogate(_)) => w.write_str(v)?,
+                Piece::Escape(_, Escaped::Char(c)) => write_escaped(w, c, ascii)?,
+                Piece::Literal(v) => {
+                    for c in v.chars() {
+                        write_escaped(w, c, ascii)?;
                     }
                 }
             }
         }
-
-        if remaining >= 0 {
-            Some(remaining as usize)
-        } else {
-            None
-        }
     }
+    w.write_char('"')
 }
 
 /// Serializes/formats the provided `Iterator` of [ScanResult]s to the writer.
@@ -408,15 +1128,29 @@ impl<'a, W: Write> Context<'a, W> {
 /// from an input than parsing a [Root] struct and using the
 /// [write_json_compact] function.
 pub fn write_json_compact_iter<'a, W, I>(w: &mut W, iter: I) -> Result<(), crate::Error>
+where
+    W: Write,
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    write_json_compact_iter_opts(w, iter, &Options::default())
+}
+
+/// Serializes/formats the provided `Iterator` of [ScanResult]s to the writer
+/// using the string options, such as [Options::with_lone_surrogates].
+///
+/// This function will ensure that the provided input is validate JSON(C),
+/// returning any error encountered.
+pub fn write_json_compact_iter_opts<'a, W, I>(
+    w: &mut W,
+    iter: I,
+    opts: &Options,
+) -> Result<(), crate::Error>
 where
     W: Write,
     I: Iterator<Item = ScanResult<'a>>,
 {
     for result in iter.validate() {
-        let event = match result {
-            Ok(event) => event,
-            Err(err) => return Err(err),
-        };
+        let event = result?;
         match event.token {
             Token::ObjectStart => w.write_char('{')?,
             Token::ObjectEnd => w.write_char('}')?,
@@ -425,11 +1159,7 @@ where
             Token::Comma => w.write_char(',')?,
             Token::Colon => w.write_char(':')?,
             Token::Null => w.write_str("null")?,
-            Token::String(v) => {
-                w.write_char('"')?;
-                w.write_str(v)?;
-                w.write_char('"')?;
-            }
+            Token::String(v) => write_json_string(w, v, opts)?,
             Token::Number(v) => w.write_str(v)?,
             Token::Bool(v) => w.write_str(if v { "true" } else { "false" })?,
             _ => {}
@@ -448,10 +1178,28 @@ where
 /// serialize compact JSON from an input than parsing a [Root] struct and using
 /// this function.
 pub fn write_json_compact<W: Write>(w: &mut W, root: &Root) -> Result<(), Error> {
-    write_json_value_compact(w, &root.value)
+    write_json_compact_opts(w, root, &Options::default())
+}
+
+/// Serializes/formats the provided JSON [Root] value to the writer as valid,
+/// compact JSON using the string options, such as
+/// [Options::with_lone_surrogates].
+pub fn write_json_compact_opts<W: Write>(
+    w: &mut W,
+    root: &Root,
+    opts: &Options,
+) -> Result<(), Error> {
+    if let Some(order) = opts.sort_keys {
+        return write_json_value_compact(w, &sorted_root(root, order).value, opts);
+    }
+    write_json_value_compact(w, &root.value, opts)
 }
 
-fn write_json_value_compact<W: Write>(w: &mut W, value: &Value) -> Result<(), Error> {
+pub(crate) fn write_json_value_compact<W: Write>(
+    w: &mut W,
+    value: &Value,
+    opts: &Options,
+) -> Result<(), Error> {
     match &value.token {
         ValueToken::Object(vals) => {
             w.write_char('{')?;
@@ -463,10 +1211,9 @@ fn write_json_value_compact<W: Write>(w: &mut W, value: &Value) -> Result<(), Er
                     } else {
                         w.write_char(',')?;
                     }
-                    w.write_char('"')?;
-                    w.write_str(k)?;
-                    w.write_str("\":")?;
-                    write_json_value_compact(w, v)?;
+                    write_json_string(w, k, opts)?;
+                    w.write_char(':')?;
+                    write_json_value_compact(w, v, opts)?;
                 }
             }
             w.write_char('}')?;
@@ -481,16 +1228,12 @@ fn write_json_value_compact<W: Write>(w: &mut W, value: &Value) -> Result<(), Er
                     } else {
                         w.write_char(',')?;
                     }
-                    write_json_value_compact(w, v)?;
+                    write_json_value_compact(w, v, opts)?;
                 }
             }
             w.write_char(']')?;
         }
-        ValueToken::String(v) => {
-            w.write_char('"')?;
-            w.write_str(v)?;
-            w.write_char('"')?;
-        }
+        ValueToken::String(v) => write_json_string(w, v, opts)?,
         ValueToken::Number(v) => w.write_str(v)?,
         ValueToken::Bool(v) => {
             if *v {
@@ -507,40 +1250,54 @@ fn write_json_value_compact<W: Write>(w: &mut W, value: &Value) -> Result<(), Er
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{ast::parse, scanner::Scanner};
+    use crate::{
+        ast::{parse, parse_iter},
+        scanner::Scanner,
+    };
 
-    #[test]
-    fn test_format() {
-        let input = r#"
-        // This is a comment.
-        // Second line.
+    /// Formats the root, checking that formatting the output again doesn't
+    /// change it.
+    fn format(root: &Root, opts: &Options) -> String {
+        let mut out = String::new();
+        write_jsonc_opts(&mut out, root, opts).unwrap();
+        let mut out2 = String::new();
+        write_jsonc_opts(&mut out2, &parse(&out).unwrap(), opts).unwrap();
+        assert_eq!(out2, out, "formatting the output again changed it");
+        out
+    }
 
-        // Break, than third.
+    const INPUT: &str = r#"
+    // This is a comment.
+    // Second line.
 
-        { // Object start.
+    // Break, than third.
 
-            "key1": "val1", // Same line comment.
-            "k": "v",
-            // Next line comment.
-            "arr_key": [ // Array start.
+    { // Object start.
 
-                "val1"
-                ,
-                100 // Before comma
-                ,
+        "key1": "val1", // Same line comment.
+        "k": "v",
+        // Next line comment.
+        "arr_key": [ // Array start.
 
-                // True.
-                true,
-            ],
+            "val1"
+            ,
+            100 // Before comma
+            ,
 
-            // And another.
-        "key2": { "nested": // And another one.
-        100, "value": true, "third": "this"
+            // True.
+            true,
+        ],
 
-        // Weird comment before comma.
-        , "is": "a", "v":{"another" :"object",}  },
-        } // Trailing comment."#;
+        // And another.
+    "key2": { "nested": // And another one.
+    100, "value": true, "third": "this"
 
+    // Weird comment before comma.
+    , "is": "a", "v":{"another" :"object",}  },
+    } // Trailing comment."#;
+
+    #[test]
+    fn test_format() {
         let expected_jsonc = r#"// This is a comment.
 // Second line.
 
@@ -575,10 +1332,9 @@ mod tests {
   }
 } // Trailing comment.
 "#;
-        let root = parse(input).unwrap();
+        let root = parse(INPUT).unwrap();
 
-        let mut jsonc = String::new();
-        write_jsonc(&mut jsonc, &root).unwrap();
+        let jsonc = format(&root, &Options::default());
         assert_eq!(&jsonc, expected_jsonc);
 
         // Parse and reformat the jsonc output. The reformatted output should
@@ -601,11 +1357,539 @@ mod tests {
         assert_eq!(&json_compact2, &json_compact);
 
         let mut json_compact_iter = String::new();
-        write_json_compact_iter(&mut json_compact_iter, Scanner::new(input)).unwrap();
+        write_json_compact_iter(&mut json_compact_iter, Scanner::new(INPUT)).unwrap();
         assert_eq!(&json_compact_iter, expected_json_compact);
 
         let mut json_compact_iter2 = String::new();
         write_json_compact_iter(&mut json_compact_iter2, Scanner::new(&json_compact_iter)).unwrap();
         assert_eq!(&json_compact_iter2, &json_compact_iter);
     }
+
+    #[test]
+    fn test_format_lone_surrogates() {
+        let input = r#"{ "\uD800": ["\uD83D\uDE00", "a\\uDE00\uDE00b"] }"#;
+        let root = parse(input).unwrap();
+
+        let opts = Options::default().with_lone_surrogates(LoneSurrogates::Replace);
+        let jsonc = format(&root, &opts);
+        assert_eq!(
+            &jsonc,
+            "{\n  \"\\uFFFD\": [\"\\uD83D\\uDE00\", \"a\\\\uDE00\\uFFFDb\"]\n}\n"
+        );
+
+        let expected_compact = r#"{"\uFFFD":["\uD83D\uDE00","a\\uDE00\uFFFDb"]}"#;
+        let mut compact = String::new();
+        write_json_compact_opts(&mut compact, &root, &opts).unwrap();
+        assert_eq!(&compact, expected_compact);
+        let mut compact_iter = String::new();
+        write_json_compact_iter_opts(&mut compact_iter, Scanner::new(input), &opts).unwrap();
+        assert_eq!(&compact_iter, expected_compact);
+
+        let mut preserved = String::new();
+        write_json_compact(&mut preserved, &root).unwrap();
+        assert_eq!(
+            &preserved,
+            r#"{"\uD800":["\uD83D\uDE00","a\\uDE00\uDE00b"]}"#
+        );
+
+        let scanner = Scanner::new(input).with_reject_lone_surrogates(true);
+        assert_eq!(
+            parse_iter(scanner).unwrap_err(),
+            crate::Error::LoneSurrogate(3..9)
+        );
+    }
+
+    #[test]
+    fn test_format_line_ending() {
+        let input = "// Comment.\r\n{\r\n  /*\n   * Block.\r\n   */\r\n  \"key\": [1, 2]\r\n}";
+
+        let root = parse(input).unwrap();
+        let lf = format(&root, &Options::default());
+        assert_eq!(
+            &lf,
+            "// Comment.\n{\n  /*\n   * Block.\n   */\n  \"key\": [1, 2]\n}\n"
+        );
+
+        let expected_crlf = lf.replace('\n', "\r\n");
+        for line_ending in [LineEndingStyle::Crlf, LineEndingStyle::Auto] {
+            let opts = Options::default().with_line_ending(line_ending);
+            let crlf = format(&root, &opts);
+            assert_eq!(&crlf, &expected_crlf);
+        }
+
+        let root = parse(&lf).unwrap();
+        let opts = Options::default().with_line_ending(LineEndingStyle::Auto);
+        let auto = format(&root, &opts);
+        assert_eq!(&auto, &lf);
+    }
+
+    #[test]
+    fn test_format_sort_keys() {
+        let input = "{\n  // Documents b.\n  \"b\": {\"y\": 1, \"x\": 2},\n  \"a\": true // A.\n}";
+        let root = parse(input).unwrap();
+        let opts = Options::default().with_sort_keys(KeyOrder::Alphabetical);
+
+        let jsonc = format(&root, &opts);
+        let expected = "{\n  \"a\": true, // A.\n  // Documents b.\n  \"b\": {\n    \"x\": 2,\n    \"y\": 1\n  }\n}\n";
+        assert_eq!(&jsonc, expected);
+
+        let mut compact = String::new();
+        write_json_compact_opts(&mut compact, &root, &opts).unwrap();
+        assert_eq!(&compact, r#"{"a":true,"b":{"x":2,"y":1}}"#);
+    }
+
+    #[test]
+    fn test_format_trailing_commas() {
+        let input =
+            "{\"a\": [1, 2], \"b\": [\n1,\n2 // Two.\n], \"c\": {\"d\": {}}, \"e\": [] // E.\n}";
+        let multiline = r#"{
+  "a": [1, 2],
+  "b": [
+    1,
+    2, // Two.
+  ],
+  "c": {
+    "d": {},
+  },
+  "e": [], // E.
+}
+"#;
+        let never = r#"{
+  "a": [1, 2],
+  "b": [
+    1,
+    2 // Two.
+  ],
+  "c": {
+    "d": {}
+  },
+  "e": [] // E.
+}
+"#;
+        let cases = [
+            (TrailingCommas::Never, never.to_string()),
+            (TrailingCommas::Multiline, multiline.to_string()),
+            (
+                TrailingCommas::Always,
+                multiline.replace("[1, 2]", "[1, 2,]"),
+            ),
+        ];
+        for (trailing_commas, expected) in cases {
+            let opts = Options::default().with_trailing_commas(trailing_commas);
+            let out = format(&parse(input).unwrap(), &opts);
+            assert_eq!(out, expected, "{trailing_commas:?}");
+        }
+
+        // Comments after the last value don't move the trailing comma.
+        let input = "{\"a\": 1, \"b\": [1, 2\n// Two.\n]\n// B.\n}";
+        let never = "{\n  \"a\": 1,\n  \"b\": [\n    1,\n    2\n    // Two.\n  ]\n  // B.\n}\n";
+        let multiline = never.replace("2\n", "2,\n").replace("]\n", "],\n");
+        let cases = [
+            (TrailingCommas::Never, never.to_string()),
+            (TrailingCommas::Multiline, multiline.clone()),
+            (TrailingCommas::Always, multiline),
+        ];
+        for (trailing_commas, expected) in cases {
+            let opts = Options::default().with_trailing_commas(trailing_commas);
+            let out = format(&parse(input).unwrap(), &opts);
+            assert_eq!(out, expected, "{trailing_commas:?}");
+        }
+    }
+
+    #[test]
+    fn test_format_preserve_expanded() {
+        let input =
+            "{\"a\": [\n1, 2], \"b\": [1, 2,], \"c\": [1, 2], \"d\": {\"x\": 1,}, \"e\": {\n}}";
+        let root = parse(input).unwrap();
+
+        let out = format(&root, &Options::default());
+        let expected = r#"{
+  "a": [1, 2],
+  "b": [1, 2],
+  "c": [1, 2],
+  "d": { "x": 1 },
+  "e": {}
+}
+"#;
+        assert_eq!(out, expected);
+
+        let opts = Options::default().with_preserve_expanded(true);
+        let out = format(&root, &opts);
+        let expected = r#"{
+  "a": [
+    1,
+    2
+  ],
+  "b": [
+    1,
+    2
+  ],
+  "c": [1, 2],
+  "d": {
+    "x": 1
+  },
+  "e": {}
+}
+"#;
+        assert_eq!(out, expected);
+
+        let opts = opts.with_trailing_commas(TrailingCommas::Always);
+        let out = format(&root, &opts);
+        assert!(out.contains("\"c\": [1, 2],\n"));
+    }
+
+    #[test]
+    fn test_format_align_values() {
+        let input = r#"{
+            "up": "k", // Up.
+            "down": "j",
+            // Comment lines don't end a group.
+            "left": "h",
+
+            "a": 1,
+            "a_much_longer_key_that_would_push_values_too_far": "a value that is long enough to overflow",
+            "abc": [1, 2],
+            "nested": {"x": 1, "y": 2},
+        }"#;
+        let opts = Options::default().with_align_values(true);
+        let out = format(&parse(input).unwrap(), &opts);
+        let expected = r#"{
+  "up":   "k", // Up.
+  "down": "j",
+  // Comment lines don't end a group.
+  "left": "h",
+
+  "a":      1,
+  "a_much_longer_key_that_would_push_values_too_far": "a value that is long enough to overflow",
+  "abc":    [1, 2],
+  "nested": {
+    "x": 1,
+    "y": 2
+  }
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_align_comments() {
+        let input = r#"{
+            "name": "fjson", // The name.
+            "version": 1, // Version.
+            "a": true,
+            "description": "a longer description", // Too far.
+
+            "x": 1, // X.
+            "list": [
+                1, // One.
+                100, /* Hundred. */
+            ], // List.
+            "y": 2, // Y.
+        }"#;
+        let opts = Options::default().with_align_comments(40);
+        let out = format(&parse(input).unwrap(), &opts);
+        let expected = r#"{
+  "name": "fjson", // The name.
+  "version": 1,    // Version.
+  "a": true,
+  "description": "a longer description", // Too far.
+
+  "x": 1, // X.
+  "list": [
+    1,  // One.
+    100 /* Hundred. */
+  ], // List.
+  "y": 2 // Y.
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_display_width() {
+        let root = parse(r#"{"a": ["漢字漢字漢字", "漢字漢字漢字"]}"#).unwrap();
+        let opts = Options::default().with_indent("\t").with_line_length(40);
+
+        // The array is 32 columns wide, so with a tab width of 4 it doesn't
+        // fit after the indent and key.
+        let out = format(&root, &opts);
+        assert_eq!(
+            out,
+            "{\n\t\"a\": [\n\t\t\"漢字漢字漢字\",\n\t\t\"漢字漢字漢字\"\n\t]\n}\n"
+        );
+
+        let out = format(&root, &opts.with_tab_width(2));
+        assert_eq!(out, "{\n\t\"a\": [\"漢字漢字漢字\", \"漢字漢字漢字\"]\n}\n");
+
+        assert_eq!(advance(0, "e\u{301}🦀", 4), 3);
+        assert_eq!(advance(5, "\tx", 4), 9);
+    }
+
+    #[test]
+    fn test_format_nested_same_line() {
+        let input = r#"{"point": {"x": 1, "y": 2}, "matrix": [[1, 2], [3, 4]], "deep": [[[1]]]}"#;
+        let root = parse(input).unwrap();
+        let opts = Options::default()
+            .with_max_object_pairs_per_line(2)
+            .with_max_line_depth(1);
+        let out = format(&root, &opts);
+        let expected = r#"{
+  "point": { "x": 1, "y": 2 },
+  "matrix": [[1, 2], [3, 4]],
+  "deep": [
+    [[1]]
+  ]
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_fill_numbers() {
+        let input = "{\"data\": [1, 22, 333, 4444, 55555, 666666, 7777777, 88888888, 9], \"mixed\": [1, \"2\", 3, 4, 5]}";
+        let root = parse(input).unwrap();
+        let opts = Options::default()
+            .with_line_length(24)
+            .with_fill_numbers(true)
+            .with_trailing_commas(TrailingCommas::Multiline);
+        let out = format(&root, &opts);
+        let expected = r#"{
+  "data": [
+    1, 22, 333, 4444,
+    55555, 666666,
+    7777777, 88888888,
+    9,
+  ],
+  "mixed": [
+    1,
+    "2",
+    3,
+    4,
+    5,
+  ],
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_idempotent() {
+        let fixtures = [
+            INPUT,
+            r#"{"a": [[1, 2], [3, [4, 5]]], "b": {"c": {"d": [1, 2, 3, 4, 5, 6]}}, "e": []}"#,
+            r#"[1, 22, 333, 4444, 55555, 666666, 7777777, 88888888, 999999999, 0]"#,
+            "{\"漢字\": [\"漢字漢字漢字\", \"🦀\"], /* Block\n   * comment.\n   */ \"x\": {}}",
+            "// Above.\n[\n  1, // One.\n\n  {\"long key\": \"long value\", \"k\": null}, // Two.\n] // End.",
+        ];
+        let base = Options::default();
+        let options = [
+            base,
+            base.with_line_length(20),
+            base.with_indent("\t").with_tab_width(8),
+            base.with_max_object_pairs_per_line(3)
+                .with_max_line_depth(2)
+                .with_fill_numbers(true),
+            base.with_trailing_commas(TrailingCommas::Multiline)
+                .with_fill_numbers(true)
+                .with_line_length(30),
+            base.with_trailing_commas(TrailingCommas::Always)
+                .with_preserve_expanded(true),
+            base.with_align_values(true)
+                .with_align_comments(40)
+                .with_sort_keys(KeyOrder::Alphabetical),
+            base.with_reindent_comments(true)
+                .with_reflow_comments(true)
+                .with_line_length(16),
+            base.with_comment_style(CommentStyle::Line)
+                .with_normalize_comment_spacing(true),
+            base.with_comment_style(CommentStyle::Block),
+        ];
+        for (i, fixture) in fixtures.iter().enumerate() {
+            for (j, opts) in options.iter().enumerate() {
+                let mut out = String::new();
+                write_jsonc_opts(&mut out, &parse(fixture).unwrap(), opts).unwrap();
+                let mut out2 = String::new();
+                write_jsonc_opts(&mut out2, &parse(&out).unwrap(), opts).unwrap();
+                assert_eq!(out2, out, "fixture {i}, options {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_reindent_comments() {
+        let input = r#"{
+                "a": {
+                        /*
+                         * Leader style.
+                         */
+                        "b": 1, /* Same line
+                                   continues. */
+                },
+                /* Relative
+                     indentation
+                   is kept. */
+                "c": 2,
+        }"#;
+        let opts = Options::default().with_reindent_comments(true);
+        let out = format(&parse(input).unwrap(), &opts);
+        let expected = r#"{
+  "a": {
+    /*
+     * Leader style.
+     */
+    "b": 1 /* Same line
+                                   continues. */
+  },
+  /* Relative
+       indentation
+     is kept. */
+  "c": 2
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_reflow_comments() {
+        let input = r#"// A short paragraph
+// stays as it is.
+{
+    // This paragraph is reflowed, since its first line is longer than the line length.
+    // It continues here.
+    //
+    // - List items aren't reflowed, even if they're longer than the line length.
+    "a": 1, // Same-line comments aren't reflowed either.
+}"#;
+        let opts = Options::default()
+            .with_line_length(40)
+            .with_reflow_comments(true);
+        let out = format(&parse(input).unwrap(), &opts);
+        let expected = r#"// A short paragraph
+// stays as it is.
+{
+  // This paragraph is reflowed, since
+  // its first line is longer than the
+  // line length. It continues here.
+  //
+  // - List items aren't reflowed, even if they're longer than the line length.
+  "a": 1 // Same-line comments aren't reflowed either.
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_comment_style() {
+        let input = r#"{
+            /**
+             * The name.
+             */
+            "name": "fjson", /* First. */ /* Last. */
+            "multi": 1, /* Two
+                lines. */
+            //No space.
+            //    Many spaces.
+            /// Doc comment.
+            /** Short doc. */
+            //
+            "line": 2, // Contains */.
+        }"#;
+        let root = parse(input).unwrap();
+
+        let opts = Options::default()
+            .with_comment_style(CommentStyle::Line)
+            .with_normalize_comment_spacing(true);
+        let out = format(&root, &opts);
+        let expected = r#"{
+  // The name.
+  "name": "fjson", /* First. */ // Last.
+  "multi": 1, /* Two
+                lines. */
+  // No space.
+  // Many spaces.
+  /// Doc comment.
+  // Short doc.
+  //
+  "line": 2 // Contains */.
+}
+"#;
+        assert_eq!(out, expected);
+
+        let opts = Options::default().with_comment_style(CommentStyle::Block);
+        let out = format(&root, &opts);
+        let expected = r#"{
+  /**
+             * The name.
+             */
+  "name": "fjson", /* First. */ /* Last. */
+  "multi": 1, /* Two
+                lines. */
+  /* No space. */
+  /* Many spaces. */
+  /// Doc comment.
+  /** Short doc. */
+  /* */
+  "line": 2 // Contains */.
+}
+"#;
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_format_string_escapes() {
+        let input = r#"{"k\u00e9y": "\u0041\/é\uD83D\uDE00😀\n\u0008\"\uD800"}"#;
+        let root = parse(input).unwrap();
+        let cases = [
+            (StringEscapes::Preserve, input.replace(": ", ":")),
+            (
+                StringEscapes::Minimal,
+                r#"{"kéy":"A/é😀😀\n\b\"\uD800"}"#.to_string(),
+            ),
+            (
+                StringEscapes::Ascii,
+                r#"{"k\u00e9y":"A/\u00e9\ud83d\ude00\ud83d\ude00\n\b\"\uD800"}"#.to_string(),
+            ),
+        ];
+        for (string_escapes, expected) in cases {
+            let opts = Options::default().with_string_escapes(string_escapes);
+
+            let mut compact = String::new();
+            write_json_compact_opts(&mut compact, &root, &opts).unwrap();
+            assert_eq!(compact, expected, "{string_escapes:?}");
+            let mut compact_iter = String::new();
+            write_json_compact_iter_opts(&mut compact_iter, Scanner::new(input), &opts).unwrap();
+            assert_eq!(compact_iter, expected, "{string_escapes:?}");
+
+            format(&root, &opts);
+        }
+
+        let opts = Options::default()
+            .with_string_escapes(StringEscapes::Minimal)
+            .with_lone_surrogates(LoneSurrogates::Replace);
+        let jsonc = format(&root, &opts);
+        assert_eq!(jsonc, "{\n  \"kéy\": \"A/é😀😀\\n\\b\\\"\u{FFFD}\"\n}\n");
+
+        // Alignment uses the keys as they're written.
+        let input = r#"{"\u00e9\u00e9\u00e9": 1, // E.
+            "abcd": 2, // A.
+        }"#;
+        let root = parse(input).unwrap();
+        let opts = Options::default()
+            .with_align_values(true)
+            .with_align_comments(40);
+        let cases = [
+            (
+                StringEscapes::Minimal,
+                "{\n  \"ééé\":  1, // E.\n  \"abcd\": 2  // A.\n}\n",
+            ),
+            (
+                StringEscapes::Ascii,
+                "{\n  \"\\u00e9\\u00e9\\u00e9\": 1, // E.\n  \"abcd\":               2  // A.\n}\n",
+            ),
+        ];
+        for (string_escapes, expected) in cases {
+            let jsonc = format(&root, &opts.with_string_escapes(string_escapes));
+            assert_eq!(jsonc, expected, "{string_escapes:?}");
+        }
+    }
 }
@@ -0,0 +1,484 @@
+//! Incrementally re-parse JSONC after an edit, for use in editors.
+//!
+//! A [Tree] holds a parsed [Root] along with the location of every object and
+//! array in the source. When the source is edited, only the innermost container
+//! that encloses the edit is scanned and parsed again; every other part of the
+//! tree is reused by pointing it at the new source.
+
+use std::ops::Range;
+
+use crate::{
+    ast::{self, ArrayValue, Comment, Metadata, ObjectValue, Root, Value, ValueToken},
+    error::Error,
+    scanner::{Event, LineEndings, Scanner, Token},
+};
+
+/// Edit describes a change to a source string: the bytes in `range` are
+/// replaced with `text`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edit<'a> {
+    pub range: Range<usize>,
+    pub text: &'a str,
+}
+
+impl<'a> Edit<'a> {
+    /// Creates a new Edit that replaces `range` with `text`.
+    pub fn new(range: Range<usize>, text: &'a str) -> Self {
+        Self { range, text }
+    }
+
+    /// Returns a copy of `source` with the edit applied.
+    ///
+    /// Panics if the range is out of bounds or not on a char boundary.
+    pub fn apply(&self, source: &str) -> String {
+        let mut out = source.to_string();
+        out.replace_range(self.range.clone(), self.text);
+        out
+    }
+
+    fn shift(&self, i: usize) -> usize {
+        if i >= self.range.end {
+            i - self.range.len() + self.text.len()
+        } else {
+            i
+        }
+    }
+}
+
+/// Tree is a parsed JSONC source that can be updated incrementally.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tree<'a> {
+    root: Root<'a>,
+    source: &'a str,
+    spans: Vec<Span>,
+    line_endings: LineEndings,
+}
+
+/// Reparse is the result of applying an [Edit] to a [Tree]. The `changed`
+/// field is the range of the new source that was scanned again; everything
+/// outside of it was reused from the previous tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reparse<'a> {
+    pub tree: Tree<'a>,
+    pub changed: Range<usize>,
+}
+
+/// Span is the location of an object or array in the source. Spans are stored
+/// in the order that their containers start, so the containers nested inside
+/// of a span immediately follow it.
+#[derive(Clone, Debug, PartialEq)]
+struct Span {
+    range: Range<usize>,
+    depth: usize,
+    descendants: usize,
+    line_endings: LineEndings,
+}
+
+impl<'a> Tree<'a> {
+    /// Parses the provided source into a Tree.
+    pub fn parse(source: &'a str) -> Result<Self, Error> {
+        let mut spans = Spans::new(0, 0);
+        let root = ast::parse_iter(Scanner::new(source).inspect(|result| {
+            if let Ok(event) = result {
+                spans.add(event);
+            }
+        }))?;
+        Ok(Tree {
+            root,
+            source,
+            spans: spans.spans,
+            line_endings: spans.line_endings,
+        })
+    }
+
+    /// Returns the parsed `Root`.
+    pub fn root(&self) -> &Root<'a> {
+        &self.root
+    }
+
+    /// Returns the source that was parsed.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Consumes the Tree, returning the parsed `Root`.
+    pub fn into_root(self) -> Root<'a> {
+        self.root
+    }
+
+    /// Returns a Tree for `source`, which must be the source of this Tree with
+    /// `edit` applied (see [Edit::apply]).
+    ///
+    /// Only the innermost object or array that encloses the edit is parsed
+    /// again. If that fails, its enclosing containers are tried in turn before
+    /// falling back to parsing the entire source. The result is always the same
+    /// as calling [Tree::parse] on the new source, including any error.
+    pub fn reparse<'b>(&self, source: &'b str, edit: &Edit) -> Result<Reparse<'b>, Error> {
+        if self.is_edited(source, edit) {
+            let path = self.enclosing(&edit.range);
+            for (n, &i) in path.iter().enumerate().rev() {
+                let span = &self.spans[i];
+                let range = span.range.start..edit.shift(span.range.end);
+                let mut spans = Spans::new(range.start, span.depth);
+                let scanner = Scanner::new(&source[range.clone()]).inspect(|result| {
+                    if let Ok(event) = result {
+                        spans.add(event);
+                    }
+                });
+                if let Ok(value) = ast::parse_nested(scanner, span.depth as u8) {
+                    let tree = self.rebuild(source, edit, &path[..n], i, value, spans);
+                    return Ok(Reparse {
+                        tree,
+                        changed: range,
+                    });
+                }
+            }
+        }
+        Ok(Reparse {
+            tree: Tree::parse(source)?,
+            changed: 0..source.len(),
+        })
+    }
+
+    /// Returns true if `source` looks like this Tree's source with `edit`
+    /// applied.
+    fn is_edited(&self, source: &str, edit: &Edit) -> bool {
+        let Range { start, end } = edit.range;
+        start <= end
+            && self.source.get(start..end).is_some()
+            && source.len() == self.source.len() - edit.range.len() + edit.text.len()
+            && source.get(start..(start + edit.text.len())) == Some(edit.text)
+    }
+
+    /// Returns the indexes of all spans that strictly enclose `range`,
+    /// outermost first. The opening and closing brackets of each container are
+    /// not part of `range`.
+    fn enclosing(&self, range: &Range<usize>) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut i = 0;
+        while let Some(span) = self.spans.get(i) {
+            if span.range.start >= range.start {
+                break;
+            }
+            if range.end < span.range.end {
+                path.push(i);
+                i += 1;
+            } else {
+                i += 1 + span.descendants;
+            }
+        }
+        path
+    }
+
+    /// Builds the Tree for `source`, replacing the container at span index
+    /// `target` with `value`.
+    fn rebuild<'b>(
+        &self,
+        source: &'b str,
+        edit: &Edit,
+        ancestors: &[usize],
+        target: usize,
+        value: Value<'b>,
+        new: Spans,
+    ) -> Tree<'b> {
+        let old = &self.spans[target];
+        let (old_descendants, old_line_endings) = (old.descendants, old.line_endings);
+        let (new_descendants, new_line_endings) = (new.spans[0].descendants, new.line_endings);
+
+        let mut spans = self.spans[..target].to_vec();
+        for &i in ancestors {
+            let span = &mut spans[i];
+            span.range.end = edit.shift(span.range.end);
+            span.descendants = span.descendants - old_descendants + new_descendants;
+            span.line_endings.sub(old_line_endings);
+            span.line_endings.add(new_line_endings);
+        }
+        spans.extend(new.spans);
+        spans.extend(
+            self.spans[(target + 1 + old_descendants)..]
+                .iter()
+                .map(|span| Span {
+                    range: edit.shift(span.range.start)..edit.shift(span.range.end),
+                    ..span.clone()
+                }),
+        );
+
+        let mut line_endings = self.line_endings;
+        line_endings.sub(old_line_endings);
+        line_endings.add(new_line_endings);
+
+        let mut rebase = Rebase {
+            old: self.source,
+            new: source,
+            edit,
+            target,
+            skip: old_descendants,
+            next: 0,
+            replacement: Some(value),
+        };
+        let root = Root {
+            meta_above: self
+                .root
+                .meta_above
+                .iter()
+                .map(|m| rebase.meta(m))
+                .collect(),
+            value: rebase.value(&self.root.value),
+            meta_below: self
+                .root
+                .meta_below
+                .iter()
+                .map(|m| rebase.meta(m))
+                .collect(),
+            line_ending: line_endings.dominant(),
+        };
+
+        Tree {
+            root,
+            source,
+            spans,
+            line_endings,
+        }
+    }
+}
+
+/// Spans records the containers and line endings seen in a stream of events
+/// that starts at `offset` in the source.
+struct Spans {
+    spans: Vec<Span>,
+    open: Vec<usize>,
+    line_endings: LineEndings,
+    offset: usize,
+    depth: usize,
+}
+
+impl Spans {
+    fn new(offset: usize, depth: usize) -> Self {
+        Self {
+            spans: Vec::new(),
+            open: Vec::new(),
+            line_endings: LineEndings::default(),
+            offset,
+            depth,
+        }
+    }
+
+    fn add(&mut self, event: &Event) {
+        match event.token {
+            Token::ObjectStart | Token::ArrayStart => {
+                let start = self.offset + event.range.start;
+                self.open.push(self.spans.len());
+                self.spans.push(Span {
+                    range: start..start,
+                    depth: self.depth + self.open.len() - 1,
+                    descendants: 0,
+                    line_endings: LineEndings::default(),
+                });
+            }
+            Token::ObjectEnd | Token::ArrayEnd => {
+                if let Some(i) = self.open.pop() {
+                    self.spans[i].range.end = self.offset + event.range.end;
+                    self.spans[i].descendants = self.spans.len() - i - 1;
+                }
+            }
+            _ => {
+                let mut counts = LineEndings::default();
+                counts.add_event(event);
+                self.line_endings.add(counts);
+                for &i in &self.open {
+                    self.spans[i].line_endings.add(counts);
+                }
+            }
+        }
+    }
+}
+
+/// Rebase copies a `Root` to point at the edited source, replacing the
+/// container that was parsed again.
+struct Rebase<'a, 'b, 'e> {
+    old: &'a str,
+    new: &'b str,
+    edit: &'e Edit<'e>,
+    target: usize,
+    skip: usize,
+    next: usize,
+    replacement: Option<Value<'b>>,
+}
+
+impl<'a, 'b> Rebase<'a, 'b, '_> {
+    fn str(&self, s: &'a str) -> &'b str {
+        let start = self
+            .edit
+            .shift(s.as_ptr() as usize - self.old.as_ptr() as usize);
+        &self.new[start..(start + s.len())]
+    }
+
+    fn value(&mut self, value: &Value<'a>) -> Value<'b> {
+        let mut expanded = value.expanded;
+        let token = match &value.token {
+            ValueToken::Object(vals) => match self.container() {
+                Some(replacement) => {
+                    expanded = replacement.expanded;
+                    replacement.token
+                }
+                None => ValueToken::Object(vals.iter().map(|v| self.object_value(v)).collect()),
+            },
+            ValueToken::Array(vals) => match self.container() {
+                Some(replacement) => {
+                    expanded = replacement.expanded;
+                    replacement.token
+                }
+                None => ValueToken::Array(vals.iter().map(|v| self.array_value(v)).collect()),
+            },
+            ValueToken::String(v) => ValueToken::String(self.str(v)),
+            ValueToken::Number(v) => ValueToken::Number(self.str(v)),
+            ValueToken::Bool(v) => ValueToken::Bool(*v),
+            ValueToken::Null => ValueToken::Null,
+        };
+        Value {
+            token,
+            comments: value.comments.iter().map(|c| self.comment(c)).collect(),
+            expanded,
+        }
+    }
+
+    /// Advances past the next container, returning its replacement if it is
+    /// the target.
+    fn container(&mut self) -> Option<Value<'b>> {
+        let i = self.next;
+        self.next += 1;
+        if i == self.target {
+            self.next += self.skip;
+            self.replacement.take()
+        } else {
+            None
+        }
+    }
+
+    fn object_value(&mut self, value: &ObjectValue<'a>) -> ObjectValue<'b> {
+        match value {
+            ObjectValue::Metadata(m) => ObjectValue::Metadata(self.meta(m)),
+            ObjectValue::KeyVal(k, v) => ObjectValue::KeyVal(self.str(k), self.value(v)),
+        }
+    }
+
+    fn array_value(&mut self, value: &ArrayValue<'a>) -> ArrayValue<'b> {
+        match value {
+            ArrayValue::Metadata(m) => ArrayValue::Metadata(self.meta(m)),
+            ArrayValue::ArrayVal(v) => ArrayValue::ArrayVal(self.value(v)),
+        }
+    }
+
+    fn meta(&self, meta: &Metadata<'a>) -> Metadata<'b> {
+        match meta {
+            Metadata::Comment(c) => Metadata::Comment(self.comment(c)),
+            Metadata::Newline => Metadata::Newline,
+        }
+    }
+
+    fn comment(&self, comment: &Comment<'a>) -> Comment<'b> {
+        match comment {
+            Comment::Line(c) => Comment::Line(self.str(c)),
+            Comment::Block(c) => Comment::Block(self.str(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "// Settings.\r\n{\r\n  \"name\": \"fjson\", // Name.\r\n  \"list\": [1, [2, 3], {\"k\": null}],\r\n  /* Block\r\n   comment */\r\n  \"nested\": {\"a\": {\"b\": [true]}}\r\n} // End.\r\n";
+
+    fn assert_reparse(input: &str, edit: &Edit) -> Option<Range<usize>> {
+        let tree = Tree::parse(input).unwrap();
+        let source = edit.apply(input);
+        let expected = Tree::parse(&source);
+        let output = tree.reparse(&source, edit);
+        assert_eq!(
+            output.as_ref().map(|r| &r.tree),
+            expected.as_ref(),
+            "edit: {edit:?}"
+        );
+        output.ok().map(|r| r.changed)
+    }
+
+    #[test]
+    fn test_reparse() {
+        let edits = [
+            (Edit::new(12..12, "!"), 0..156),
+            (Edit::new(20..24, "project"), 14..148),
+            (Edit::new(57..58, "10"), 56..81),
+            (Edit::new(62..65, ""), 60..63),
+            (Edit::new(67..67, "\r\n"), 56..82),
+            (Edit::new(74..78, "true"), 68..79),
+            (Edit::new(85..108, ""), 14..122),
+            (Edit::new(135..139, "false, \"x\""), 134..146),
+        ];
+        for (edit, changed) in edits {
+            assert_eq!(
+                assert_reparse(INPUT, &edit),
+                Some(changed),
+                "edit: {edit:?}"
+            );
+        }
+
+        // Edits that break the enclosing container fall back to its parents,
+        // and eventually the entire source.
+        assert_eq!(
+            assert_reparse(INPUT, &Edit::new(62..62, "], [4")),
+            Some(56..85)
+        );
+        assert_eq!(assert_reparse(INPUT, &Edit::new(61..61, "/*")), None);
+        assert_eq!(assert_reparse(INPUT, &Edit::new(62..62, "]")), None);
+        assert_eq!(assert_reparse(INPUT, &Edit::new(137..137, "\"")), None);
+    }
+
+    #[test]
+    fn test_reparse_generated() {
+        const FRAGMENTS: &[&str] = &[
+            "",
+            "{",
+            "}",
+            "[",
+            "]",
+            ",",
+            ":",
+            " ",
+            "\n",
+            "\r\n",
+            "\"",
+            "\"s\"",
+            "1",
+            "-2.5",
+            "null",
+            "true",
+            "// c\n",
+            "/*",
+            "*/",
+            "/* c */",
+            "{\"k\": [1]}",
+            "[[]]",
+            "\\",
+        ];
+        let inputs = [INPUT, "[]", "[[[1], 2], {\"a\": [3]}]\n// c"];
+
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut rand = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        for _ in 0..2000 {
+            let input = inputs[rand(inputs.len())];
+            let start = rand(input.len() + 1);
+            let end = start + rand(input.len() - start + 1).min(4);
+            if !input.is_char_boundary(start) || !input.is_char_boundary(end) {
+                continue;
+            }
+            let text = FRAGMENTS[rand(FRAGMENTS.len())];
+            assert_reparse(input, &Edit::new(start..end, text));
+        }
+    }
+}
@@ -151,13 +151,29 @@
 #![forbid(unsafe_code)]
 
 pub mod ast;
+pub mod attached;
+pub mod byte_scanner;
+pub mod diff;
+mod doc;
+pub mod encoding;
 pub mod error;
+mod escape;
+pub mod extract;
 pub mod format;
+pub mod incremental;
+pub mod merge;
+pub mod number;
+pub mod patch;
+pub mod pointer;
+pub mod pull;
 pub mod scanner;
+pub mod sort;
 pub mod validate;
+pub mod visit;
 
 use std::fmt::Write;
 
+use byte_scanner::ByteScanner;
 pub use error::Error;
 use scanner::Scanner;
 
@@ -171,6 +187,15 @@ pub fn to_jsonc(input: &str) -> Result<String, Error> {
     Ok(out)
 }
 
+/// Parses and formats JSON with C-style comments and trailing commas from raw
+/// bytes.
+///
+/// The input is decoded with [encoding::decode], which skips a leading byte
+/// order mark and transcodes UTF-16 input.
+pub fn to_jsonc_bytes(input: &[u8]) -> Result<String, Error> {
+    to_jsonc(&encoding::decode(input)?)
+}
+
 /// Parses and formats JSON with C-style comments and trailing commas to the
 /// provided writer.
 ///
@@ -192,6 +217,15 @@ pub fn to_json(input: &str) -> Result<String, Error> {
     Ok(out)
 }
 
+/// Parses JSONC from raw bytes and formats the output into "pretty" printed
+/// JSON.
+///
+/// The input is decoded with [encoding::decode], which skips a leading byte
+/// order mark and transcodes UTF-16 input.
+pub fn to_json_bytes(input: &[u8]) -> Result<String, Error> {
+    to_json(&encoding::decode(input)?)
+}
+
 /// Parses JSONC and formats the output into "pretty" printed JSON to the
 /// provided writer.
 ///
@@ -213,13 +247,24 @@ pub fn to_json_compact(input: &str) -> Result<String, Error> {
     Ok(out)
 }
 
+/// Parses JSONC from raw bytes and formats the output into valid, compact JSON.
+///
+/// The input is decoded with [encoding::decode], which skips a leading byte
+/// order mark and transcodes UTF-16 input.
+pub fn to_json_compact_bytes(input: &[u8]) -> Result<String, Error> {
+    to_json_compact(&encoding::decode(input)?)
+}
+
 /// Parses JSONC and formats the output into valid, compact JSON to the provided
 /// writer.
 ///
 /// All comments and whitespace are stripped from the input and is formatted to
 /// be compact JSON, not intended for consumption by humans.
+///
+/// The input is tokenized with a [ByteScanner], which is faster than a
+/// [Scanner] for large inputs.
 pub fn to_json_writer_compact<W: Write>(w: &mut W, input: &str) -> Result<(), Error> {
-    format::write_json_compact_iter(w, Scanner::new(input).without_metadata())?;
+    format::write_json_compact_iter(w, ByteScanner::new(input).without_metadata())?;
     Ok(())
 }
 
@@ -321,6 +366,28 @@ mod tests {
         let _: serde_json::Value = serde_json::from_str(&out).expect("unable to parse json output");
     }
 
+    #[test]
+    fn test_bytes() {
+        let expected = "{\n  \"key\": \"é\"\n} // Comment.\n";
+        assert_eq!(to_jsonc_bytes(b"{\"key\":\"\xC3\xA9\"} // Comment.").unwrap(), expected);
+        assert_eq!(
+            to_jsonc_bytes(b"\xEF\xBB\xBF{\"key\":\"\xC3\xA9\"} // Comment.").unwrap(),
+            expected
+        );
+
+        let utf16: Vec<u8> = "\u{feff}{\"key\":\"é\"}"
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes())
+            .collect();
+        assert_eq!(to_json_bytes(&utf16).unwrap(), "{\n  \"key\": \"é\"\n}\n");
+        assert_eq!(to_json_compact_bytes(&utf16).unwrap(), "{\"key\":\"é\"}");
+
+        assert_eq!(
+            to_json_compact_bytes(b"{\"key\":\"\xC3\"}"),
+            Err(Error::InvalidUtf8(8))
+        );
+    }
+
     #[test]
     fn test_to_json_compact() {
         let expected = r#"{"key1":"val1","k":"v","arr_key":["val1",100,true],"key2":{"nested":100,"value":true,"third":"this","is":"a","v":{"another":"object"}}}"#;
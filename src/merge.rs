@@ -0,0 +1,196 @@
+//! Apply a JSON Merge Patch ([RFC 7396](https://www.rfc-editor.org/rfc/rfc7396))
+//! to a commented document.
+
+use crate::{
+    ast::{strip_meta_value, ObjectValue, Root, Value, ValueToken},
+    attached::{Member, Object},
+    escape::unescape,
+};
+
+/// MergeOptions control how comments are handled by [merge_patch_opts].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MergeOptions {
+    patch_comments: bool,
+}
+
+impl MergeOptions {
+    /// Sets whether comments in the patch are kept. When enabled, keys that are
+    /// added to the target keep the comments above them and on the same line,
+    /// and values that replace a target value keep the comments inside of
+    /// them. The default is false.
+    pub fn with_patch_comments(self, patch_comments: bool) -> Self {
+        Self { patch_comments }
+    }
+}
+
+/// Applies the merge patch to the target according to the default options.
+/// See [merge_patch_opts] for details.
+pub fn merge_patch<'a>(target: &mut Root<'a>, patch: &Root<'a>) {
+    merge_patch_opts(target, patch, &MergeOptions::default());
+}
+
+/// Applies the merge patch to the target.
+///
+/// Members of the target that are patched to `null` are removed along with
+/// the comments attached to them. Members that are replaced or merged keep
+/// their position and the comments from the target. New members are added to
+/// the end of their object.
+pub fn merge_patch_opts<'a>(target: &mut Root<'a>, patch: &Root<'a>, opts: &MergeOptions) {
+    merge_value(&mut target.value, &patch.value, opts);
+}
+
+fn merge_value<'a>(target: &mut Value<'a>, patch: &Value<'a>, opts: &MergeOptions) {
+    let ValueToken::Object(patch_vals) = &patch.token else {
+        let mut value = patch.clone();
+        if !opts.patch_comments {
+            strip_meta_value(&mut value);
+        }
+        target.replace_token(value);
+        return;
+    };
+    let vals = match &mut target.token {
+        ValueToken::Object(vals) => vals,
+        token => {
+            *token = ValueToken::Object(Vec::new());
+            target.expanded = false;
+            let ValueToken::Object(vals) = token else {
+                unreachable!()
+            };
+            vals
+        }
+    };
+
+    let mut object = Object::from(std::mem::take(vals));
+    for member in Object::from(patch_vals.clone()).members {
+        let key = unescape(member.key);
+        if member.value.token == ValueToken::Null {
+            while object.remove(&key).is_some() {}
+            continue;
+        }
+        if let Some(existing) = object.get_mut(&key) {
+            merge_value(&mut existing.value, &member.value, opts);
+            continue;
+        }
+        let mut value = Value::new(ValueToken::Null, Vec::new());
+        merge_value(&mut value, &member.value, opts);
+        let mut added = Member::new(member.key, value);
+        if opts.patch_comments {
+            added.comments = member.comments;
+            added.value.comments = member.value.comments;
+        }
+        object.members.push(added);
+    }
+    *vals = Vec::<ObjectValue>::from(object);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse;
+
+    const BASE: &str = r#"// Base config.
+    {
+        // The service name.
+        "name": "api", // Same line.
+        // Debug logging.
+        "debug": true,
+
+        // Database settings.
+        "db": {
+            "host": "localhost", // Host.
+            "port": 5432,
+        },
+        "tags": ["a", "b"],
+    }"#;
+
+    const PATCH: &str = r#"{
+        "name": "api-prod", // Name.
+        "debug": null,
+        "db": {"host": "db.internal", "pool": {"size": 10, "unused": null}},
+        // Replicas.
+        "replicas": 3, // Three.
+        "tags": [/* Only. */ "c"],
+    }"#;
+
+    #[test]
+    fn test_merge_patch() {
+        let mut root = parse(BASE).unwrap();
+        merge_patch(&mut root, &parse(PATCH).unwrap());
+        let expected = parse(
+            r#"// Base config.
+            {
+                // The service name.
+                "name": "api-prod", // Same line.
+
+                // Database settings.
+                "db": {
+                    "host": "db.internal", // Host.
+                    "port": 5432,
+                    "pool": {"size": 10},
+                },
+                "tags": ["c"],
+                "replicas": 3,
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+
+        let mut root = parse(BASE).unwrap();
+        let opts = MergeOptions::default().with_patch_comments(true);
+        merge_patch_opts(&mut root, &parse(PATCH).unwrap(), &opts);
+        let expected = parse(
+            r#"// Base config.
+            {
+                // The service name.
+                "name": "api-prod", // Same line.
+
+                // Database settings.
+                "db": {
+                    "host": "db.internal", // Host.
+                    "port": 5432,
+                    "pool": {"size": 10},
+                },
+                "tags": [/* Only. */ "c"],
+                // Replicas.
+                "replicas": 3, // Three.
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+    }
+
+    #[test]
+    fn test_merge_patch_rfc() {
+        // Test cases from Appendix A of RFC 7396.
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            let mut root = parse(target).unwrap();
+            merge_patch(&mut root, &parse(patch).unwrap());
+            assert_eq!(root, parse(expected).unwrap(), "{target} + {patch}");
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Decode JSON numbers into Rust numeric types.
+
+use std::{
+    error,
+    fmt::{self, Display},
+    num::{IntErrorKind, ParseIntError},
+    str::FromStr,
+};
+
+/// Number wraps the raw source of a JSON number, as found in
+/// `Token::Number` and `ValueToken::Number`, and decodes it on demand.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Number<'a> {
+    raw: &'a str,
+}
+
+/// NumberKind classifies a JSON number by its syntax.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NumberKind {
+    /// A number without a fraction or exponent, e.g. `-12`.
+    Integer,
+    /// A number with a fraction and/or exponent, e.g. `1.5` or `1e3`.
+    Float,
+}
+
+/// NumberError describes why a JSON number could not be converted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NumberError {
+    /// The number has a fraction or exponent, but an integer was requested.
+    NotAnInteger,
+    /// The number is outside of the range of the requested type.
+    OutOfRange,
+    /// The number cannot be converted without losing precision.
+    Lossy,
+}
+
+impl error::Error for NumberError {}
+
+impl Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnInteger => f.write_str("number is not an integer"),
+            Self::OutOfRange => f.write_str("number out of range"),
+            Self::Lossy => f.write_str("number cannot be represented without loss of precision"),
+        }
+    }
+}
+
+impl<'a> Number<'a> {
+    /// Creates a Number from the raw source of a JSON number. The source is
+    /// expected to have been validated by a
+    /// [Scanner](crate::scanner::Scanner).
+    pub fn new(raw: &'a str) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the raw source of the number.
+    pub fn as_str(&self) -> &'a str {
+        self.raw
+    }
+
+    /// Returns whether the number was written as an integer or a float.
+    pub fn kind(&self) -> NumberKind {
+        if self.raw.bytes().any(|b| matches!(b, b'.' | b'e' | b'E')) {
+            NumberKind::Float
+        } else {
+            NumberKind::Integer
+        }
+    }
+
+    /// Converts the number to an `i64`.
+    pub fn to_i64(&self) -> Result<i64, NumberError> {
+        self.parse_integer()
+    }
+
+    /// Converts the number to a `u64`.
+    pub fn to_u64(&self) -> Result<u64, NumberError> {
+        self.parse_integer()
+    }
+
+    /// Converts the number to an `i128`.
+    pub fn to_i128(&self) -> Result<i128, NumberError> {
+        self.parse_integer()
+    }
+
+    /// Converts the number to the nearest `f64`, returning an error if the
+    /// number is too large to be represented as a finite value.
+    pub fn to_f64(&self) -> Result<f64, NumberError> {
+        match self.raw.parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok(v),
+            _ => Err(NumberError::OutOfRange),
+        }
+    }
+
+    /// Converts the number to an `f64`, returning an error if the `f64` does
+    /// not hold the same decimal value as the source. For example, `0.1` is
+    /// converted, while `9007199254740993` and `1e-400` are lossy.
+    pub fn to_f64_lossless(&self) -> Result<f64, NumberError> {
+        let v = self.to_f64()?;
+        if Decimal::new(self.raw) == Decimal::new(&format!("{v:e}")) {
+            Ok(v)
+        } else {
+            Err(NumberError::Lossy)
+        }
+    }
+
+    /// Returns true if the number can be represented as a finite `f64`.
+    pub fn is_finite_f64(&self) -> bool {
+        self.to_f64().is_ok()
+    }
+
+    /// Returns true if both numbers have the same decimal value, regardless of
+    /// how they are written. For example, `1`, `1.0`, and `10e-1` are equal.
+    pub fn value_eq(&self, other: &Number) -> bool {
+        Decimal::new(self.raw) == Decimal::new(other.raw)
+    }
+
+    fn parse_integer<T: FromStr<Err = ParseIntError>>(&self) -> Result<T, NumberError> {
+        if self.kind() == NumberKind::Float {
+            return Err(NumberError::NotAnInteger);
+        }
+        let raw = if self.raw == "-0" { "0" } else { self.raw };
+        raw.parse().map_err(|err: ParseIntError| match err.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow | IntErrorKind::InvalidDigit => {
+                NumberError::OutOfRange
+            }
+            _ => NumberError::NotAnInteger,
+        })
+    }
+}
+
+/// Decimal is the normalized form of a number: its significant digits without
+/// leading or trailing zeros, and the exponent of the last digit.
+#[derive(Debug, PartialEq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn new(s: &str) -> Self {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], parse_exponent(&s[(i + 1)..])),
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let digits = [int, frac].concat();
+        let significant = digits.trim_end_matches('0');
+        let exponent = exponent
+            .saturating_sub(frac.len() as i64)
+            .saturating_add((digits.len() - significant.len()) as i64);
+        let significant = significant.trim_start_matches('0');
+        if significant.is_empty() {
+            // All zeros are equal, regardless of sign or exponent.
+            return Decimal {
+                negative: false,
+                digits: String::new(),
+                exponent: 0,
+            };
+        }
+        Decimal {
+            negative,
+            digits: significant.to_string(),
+            exponent,
+        }
+    }
+}
+
+fn parse_exponent(s: &str) -> i64 {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let v = s.bytes().fold(0i64, |acc, b| {
+        acc.saturating_mul(10).saturating_add((b - b'0') as i64)
+    });
+    if negative {
+        -v
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_integers() {
+        assert_eq!(Number::new("-12").kind(), NumberKind::Integer);
+        assert_eq!(Number::new("-12").to_i64(), Ok(-12));
+        assert_eq!(Number::new("-0").to_u64(), Ok(0));
+        assert_eq!(Number::new("-1").to_u64(), Err(NumberError::OutOfRange));
+        assert_eq!(Number::new("18446744073709551615").to_u64(), Ok(u64::MAX));
+        assert_eq!(
+            Number::new("18446744073709551616").to_i64(),
+            Err(NumberError::OutOfRange)
+        );
+        assert_eq!(
+            Number::new("-18446744073709551616").to_i128(),
+            Ok(-18446744073709551616)
+        );
+        assert_eq!(Number::new("1e3").kind(), NumberKind::Float);
+        assert_eq!(Number::new("1e3").to_i64(), Err(NumberError::NotAnInteger));
+        assert_eq!(Number::new("1.0").to_i128(), Err(NumberError::NotAnInteger));
+    }
+
+    #[test]
+    fn test_number_floats() {
+        assert_eq!(Number::new("1.5").to_f64(), Ok(1.5));
+        assert_eq!(Number::new("-2E-3").to_f64(), Ok(-0.002));
+        assert_eq!(Number::new("1e999").to_f64(), Err(NumberError::OutOfRange));
+        assert!(!Number::new("-1e999").is_finite_f64());
+        assert!(Number::new("1e-999").is_finite_f64());
+
+        assert_eq!(Number::new("0.1").to_f64_lossless(), Ok(0.1));
+        assert_eq!(Number::new("100").to_f64_lossless(), Ok(100.0));
+        assert_eq!(Number::new("0.000e10").to_f64_lossless(), Ok(0.0));
+        assert_eq!(Number::new("-0").to_f64_lossless(), Ok(-0.0));
+        assert_eq!(Number::new("1.50e2").to_f64_lossless(), Ok(150.0));
+        assert_eq!(
+            Number::new("9007199254740992").to_f64_lossless(),
+            Ok(9007199254740992.0)
+        );
+        assert_eq!(
+            Number::new("9007199254740993").to_f64_lossless(),
+            Err(NumberError::Lossy)
+        );
+        assert_eq!(
+            Number::new("1.00000000000000000001").to_f64_lossless(),
+            Err(NumberError::Lossy)
+        );
+        assert_eq!(
+            Number::new("1e-400").to_f64_lossless(),
+            Err(NumberError::Lossy)
+        );
+
+        assert!(Number::new("1").value_eq(&Number::new("10e-1")));
+        assert!(Number::new("-0.0").value_eq(&Number::new("0")));
+        assert!(!Number::new("9007199254740993").value_eq(&Number::new("9007199254740992")));
+    }
+}
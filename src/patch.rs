@@ -0,0 +1,545 @@
+//! Apply a JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902))
+//! to a commented document.
+
+use std::{
+    borrow::Cow,
+    error,
+    fmt::{self, Display, Write},
+};
+
+use crate::{
+    ast::{json_eq, ArrayValue, Comment, ObjectValue, Root, Value, ValueToken},
+    attached::{Array, Element, Member, Object},
+    error::Error,
+    escape::{escape, unescape},
+    format::{write_json_value_compact, Options},
+    pointer::{parse_index, Pointer},
+};
+
+pub use typed_arena::Arena;
+
+/// Op is the kind of a JSON Patch operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Remove,
+    Replace,
+    Move,
+    Copy,
+    Test,
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Add => "add",
+            Self::Remove => "remove",
+            Self::Replace => "replace",
+            Self::Move => "move",
+            Self::Copy => "copy",
+            Self::Test => "test",
+        })
+    }
+}
+
+/// PatchError describes why a JSON Patch operation failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PatchError {
+    /// The operation is malformed, or cannot be applied to its path.
+    Invalid,
+    /// There is no value at the path.
+    NotFound,
+    /// The value at the path of a `test` operation is not equal.
+    TestFailed,
+}
+
+impl error::Error for PatchError {}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid => f.write_str("invalid operation"),
+            Self::NotFound => f.write_str("value not found"),
+            Self::TestFailed => f.write_str("test failed"),
+        }
+    }
+}
+
+/// Operation is a single operation of a JSON Patch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation<'a> {
+    op: Op,
+    path: Pointer<'a>,
+    from: Option<Pointer<'a>>,
+    value: Option<Value<'a>>,
+}
+
+impl<'a> Operation<'a> {
+    /// Creates an `add` operation.
+    pub fn add(path: Pointer<'a>, value: Value<'a>) -> Self {
+        Self::new(Op::Add, path, None, Some(value))
+    }
+
+    /// Creates a `remove` operation.
+    pub fn remove(path: Pointer<'a>) -> Self {
+        Self::new(Op::Remove, path, None, None)
+    }
+
+    /// Creates a `replace` operation.
+    pub fn replace(path: Pointer<'a>, value: Value<'a>) -> Self {
+        Self::new(Op::Replace, path, None, Some(value))
+    }
+
+    /// Creates a `move` operation.
+    pub fn move_from(from: Pointer<'a>, path: Pointer<'a>) -> Self {
+        Self::new(Op::Move, path, Some(from), None)
+    }
+
+    /// Creates a `copy` operation.
+    pub fn copy_from(from: Pointer<'a>, path: Pointer<'a>) -> Self {
+        Self::new(Op::Copy, path, Some(from), None)
+    }
+
+    /// Creates a `test` operation.
+    pub fn test(path: Pointer<'a>, value: Value<'a>) -> Self {
+        Self::new(Op::Test, path, None, Some(value))
+    }
+
+    fn new(op: Op, path: Pointer<'a>, from: Option<Pointer<'a>>, value: Option<Value<'a>>) -> Self {
+        Self {
+            op,
+            path,
+            from,
+            value,
+        }
+    }
+
+    /// Returns the kind of operation.
+    pub fn op(&self) -> Op {
+        self.op
+    }
+
+    /// Returns the target of the operation.
+    pub fn path(&self) -> &Pointer<'a> {
+        &self.path
+    }
+
+    /// Returns the source of a `move` or `copy` operation.
+    pub fn from(&self) -> Option<&Pointer<'a>> {
+        self.from.as_ref()
+    }
+
+    /// Returns the value of an `add`, `replace`, or `test` operation.
+    pub fn value(&self) -> Option<&Value<'a>> {
+        self.value.as_ref()
+    }
+
+    /// Returns the raw key for a member added at `path`.
+    fn key(&self) -> Cow<'a, str> {
+        match self.path.tokens().last() {
+            Some(Cow::Borrowed(token)) => escape(token),
+            Some(Cow::Owned(token)) => Cow::Owned(escape(token).into_owned()),
+            None => Cow::Borrowed(""),
+        }
+    }
+}
+
+/// Parses the operations of a JSON Patch document, which is an array of
+/// operation objects.
+pub fn parse_operations<'a>(patch: &Root<'a>) -> Result<Vec<Operation<'a>>, Error> {
+    let ValueToken::Array(vals) = &patch.value.token else {
+        return Err(Error::Patch(0, String::new(), PatchError::Invalid));
+    };
+    vals.iter()
+        .filter_map(|v| match v {
+            ArrayValue::ArrayVal(v) => Some(v),
+            ArrayValue::Metadata(_) => None,
+        })
+        .enumerate()
+        .map(|(i, val)| {
+            parse_operation(val).map_err(|path| Error::Patch(i, path, PatchError::Invalid))
+        })
+        .collect()
+}
+
+/// Parses a single operation object, returning its raw path on error.
+fn parse_operation<'a>(val: &Value<'a>) -> Result<Operation<'a>, String> {
+    let ValueToken::Object(vals) = &val.token else {
+        return Err(String::new());
+    };
+    let (mut op, mut path, mut from, mut value) = (None, None, None, None);
+    for val in vals {
+        let ObjectValue::KeyVal(k, v) = val else {
+            continue;
+        };
+        match (unescape(k).as_ref(), &v.token) {
+            ("op", ValueToken::String(s)) => op = Some(unescape(s)),
+            ("path", ValueToken::String(s)) => path = Some(unescape(s)),
+            ("from", ValueToken::String(s)) => from = Some(unescape(s)),
+            ("value", _) => value = Some(v.clone()),
+            ("op" | "path" | "from", _) => return Err(String::new()),
+            _ => {}
+        }
+    }
+    let raw = path.clone().unwrap_or_default().into_owned();
+    let pointer = |s: Option<Cow<'a, str>>| match s {
+        Some(Cow::Borrowed(s)) => Pointer::parse(s).ok(),
+        Some(Cow::Owned(s)) => Pointer::parse(&s).ok().map(Pointer::into_owned),
+        None => None,
+    };
+    let path = pointer(path).ok_or_else(|| raw.clone())?;
+    let op = match (op.as_deref(), value) {
+        (Some("add"), Some(value)) => Operation::add(path, value),
+        (Some("remove"), _) => Operation::remove(path),
+        (Some("replace"), Some(value)) => Operation::replace(path, value),
+        (Some("move"), _) => Operation::move_from(pointer(from).ok_or(raw)?, path),
+        (Some("copy"), _) => Operation::copy_from(pointer(from).ok_or(raw)?, path),
+        (Some("test"), Some(value)) => Operation::test(path, value),
+        _ => return Err(raw),
+    };
+    Ok(op)
+}
+
+/// Writes the operations to the writer as a compact JSON Patch document.
+pub fn write_patch<W: Write>(w: &mut W, ops: &[Operation]) -> Result<(), Error> {
+    let opts = Options::default();
+    w.write_char('[')?;
+    for (i, op) in ops.iter().enumerate() {
+        if i > 0 {
+            w.write_char(',')?;
+        }
+        write!(w, r#"{{"op":"{}","path":""#, op.op)?;
+        w.write_str(&escape(&op.path.to_string()))?;
+        w.write_char('"')?;
+        if let Some(from) = &op.from {
+            w.write_str(r#","from":""#)?;
+            w.write_str(&escape(&from.to_string()))?;
+            w.write_char('"')?;
+        }
+        if let Some(value) = &op.value {
+            w.write_str(r#","value":"#)?;
+            write_json_value_compact(w, value, &opts)?;
+        }
+        w.write_char('}')?;
+    }
+    w.write_char(']')?;
+    Ok(())
+}
+
+/// Applies the operations of a JSON Patch to the `Root`.
+///
+/// The patch is atomic: if any operation fails, the `Root` is left unchanged
+/// and the error identifies the index of the operation and the pointer that
+/// it failed on.
+///
+/// Comments are kept for every value that isn't touched by the patch. Removed
+/// members are removed along with the comments attached to them, and moved
+/// members keep their comments. Replaced values keep the comments of the value
+/// they replace.
+///
+/// Added members whose keys must be escaped, or whose keys aren't borrowed
+/// from the source of the operations, have their keys allocated in `keys`,
+/// and the `Root` borrows them from it. The same arena can be used for every
+/// patch applied to a `Root`.
+pub fn apply_patch<'a>(
+    root: &mut Root<'a>,
+    ops: &[Operation<'a>],
+    keys: &'a Arena<String>,
+) -> Result<(), Error> {
+    let mut value = root.value.clone();
+    for (i, op) in ops.iter().enumerate() {
+        apply(&mut value, op, keys)
+            .map_err(|(pointer, err)| Error::Patch(i, pointer.to_string(), err))?;
+    }
+    root.value = value;
+    Ok(())
+}
+
+fn apply<'o, 'a>(
+    doc: &mut Value<'a>,
+    op: &'o Operation<'a>,
+    keys: &'a Arena<String>,
+) -> Result<(), (&'o Pointer<'a>, PatchError)> {
+    let path = &op.path;
+    let key = || match op.key() {
+        Cow::Borrowed(key) => key,
+        Cow::Owned(key) => keys.alloc(key).as_str(),
+    };
+    let value = || op.value.clone().ok_or((path, PatchError::Invalid));
+    match op.op {
+        Op::Add => add(doc, path, key(), Vec::new(), value()?).map_err(|err| (path, err)),
+        Op::Remove => remove(doc, path).map(drop).map_err(|err| (path, err)),
+        Op::Replace => {
+            let target = get_mut(doc, path.tokens()).ok_or((path, PatchError::NotFound))?;
+            target.replace_token(value()?);
+            Ok(())
+        }
+        Op::Move => {
+            let from = op.from.as_ref().ok_or((path, PatchError::Invalid))?;
+            get_mut(doc, from.tokens()).ok_or((from, PatchError::NotFound))?;
+            if from == path {
+                return Ok(());
+            }
+            if path.tokens().starts_with(from.tokens()) {
+                return Err((path, PatchError::Invalid));
+            }
+            let (comments, value) = remove(doc, from).map_err(|err| (from, err))?;
+            add(doc, path, key(), comments, value).map_err(|err| (path, err))
+        }
+        Op::Copy => {
+            let from = op.from.as_ref().ok_or((path, PatchError::Invalid))?;
+            let value = get_mut(doc, from.tokens())
+                .ok_or((from, PatchError::NotFound))?
+                .clone();
+            add(doc, path, key(), Vec::new(), value).map_err(|err| (path, err))
+        }
+        Op::Test => {
+            let target = get_mut(doc, path.tokens()).ok_or((path, PatchError::NotFound))?;
+            if json_eq(target, &value()?) {
+                Ok(())
+            } else {
+                Err((path, PatchError::TestFailed))
+            }
+        }
+    }
+}
+
+/// Returns the value at the path of reference tokens.
+fn get_mut<'v, 'a>(value: &'v mut Value<'a>, tokens: &[Cow<str>]) -> Option<&'v mut Value<'a>> {
+    let mut value = value;
+    for token in tokens {
+        value = match &mut value.token {
+            ValueToken::Object(vals) => vals.iter_mut().rev().find_map(|v| match v {
+                ObjectValue::KeyVal(k, v) if unescape(k) == *token => Some(v),
+                _ => None,
+            })?,
+            ValueToken::Array(vals) => vals
+                .iter_mut()
+                .filter_map(|v| match v {
+                    ArrayValue::ArrayVal(v) => Some(v),
+                    ArrayValue::Metadata(_) => None,
+                })
+                .nth(parse_index(token)?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Adds the value at `path`, with `key` as the raw key if a member is added
+/// to an object.
+fn add<'a>(
+    doc: &mut Value<'a>,
+    path: &Pointer,
+    key: &'a str,
+    comments: Vec<Comment<'a>>,
+    value: Value<'a>,
+) -> Result<(), PatchError> {
+    let Some((last, parent)) = path.tokens().split_last() else {
+        doc.replace_token(value);
+        return Ok(());
+    };
+    let parent = get_mut(doc, parent).ok_or(PatchError::NotFound)?;
+    match &mut parent.token {
+        ValueToken::Object(vals) => {
+            let mut object = Object::from(std::mem::take(vals));
+            match object.get_mut(last) {
+                Some(member) => member.value.replace_token(value),
+                None => object.members.push(Member {
+                    detached: Vec::new(),
+                    comments,
+                    key,
+                    value,
+                }),
+            }
+            *vals = object.into();
+            Ok(())
+        }
+        ValueToken::Array(vals) => {
+            let mut array = Array::from(std::mem::take(vals));
+            let len = array.elements.len();
+            let index = match last.as_ref() {
+                "-" => Some(len),
+                token => parse_index(token).filter(|i| *i <= len),
+            };
+            if let Some(index) = index {
+                // The new element takes over any metadata separating the
+                // element it is inserted before.
+                let detached = match array.elements.get_mut(index) {
+                    Some(next) => std::mem::take(&mut next.detached),
+                    None => Vec::new(),
+                };
+                let element = Element {
+                    detached,
+                    comments,
+                    value,
+                };
+                array.elements.insert(index, element);
+            }
+            *vals = array.into();
+            index.map(drop).ok_or(PatchError::NotFound)
+        }
+        _ => Err(PatchError::NotFound),
+    }
+}
+
+/// Removes the value at `path`, returning it along with the comments above
+/// it.
+fn remove<'a>(
+    doc: &mut Value<'a>,
+    path: &Pointer,
+) -> Result<(Vec<Comment<'a>>, Value<'a>), PatchError> {
+    let (last, parent) = path.tokens().split_last().ok_or(PatchError::Invalid)?;
+    let parent = get_mut(doc, parent).ok_or(PatchError::NotFound)?;
+    let removed = match &mut parent.token {
+        ValueToken::Object(vals) => {
+            let mut object = Object::from(std::mem::take(vals));
+            let removed = object.remove(last).map(|m| (m.comments, m.value));
+            *vals = object.into();
+            removed
+        }
+        ValueToken::Array(vals) => {
+            let mut array = Array::from(std::mem::take(vals));
+            let removed = parse_index(last)
+                .filter(|i| *i < array.elements.len())
+                .map(|i| array.remove(i))
+                .map(|e| (e.comments, e.value));
+            *vals = array.into();
+            removed
+        }
+        _ => None,
+    };
+    removed.ok_or(PatchError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse;
+
+    const INPUT: &str = r#"{
+        // Name.
+        "name": "fjson",
+        "tags": [
+            "json", // First.
+            "rust",
+        ],
+        "a/b": {"c": 1.0},
+    }"#;
+
+    #[test]
+    fn test_apply_patch() {
+        let patch = parse(
+            r#"[
+                {"op": "test", "path": "/a~1b/c", "value": 1},
+                {"op": "replace", "path": "/name", "value": "fjson2"},
+                {"op": "add", "path": "/tags/1", "value": "jsonc"},
+                {"op": "remove", "path": "/tags/0"},
+                {"op": "move", "from": "/name", "path": "/title"},
+                {"op": "copy", "from": "/a~1b", "path": "/quote\"d"},
+                {"op": "add", "path": "/tags/-", "value": [true]},
+            ]"#,
+        )
+        .unwrap();
+        let ops = parse_operations(&patch).unwrap();
+        let keys = Arena::new();
+        let mut root = parse(INPUT).unwrap();
+        apply_patch(&mut root, &ops, &keys).unwrap();
+        drop(ops);
+        let expected = parse(
+            r#"{
+                "tags": [
+                    "jsonc",
+                    "rust",
+                    [true],
+                ],
+                "a/b": {"c": 1.0},
+                // Name.
+                "title": "fjson2",
+                "quote\"d": {"c": 1.0},
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+
+        // Replaced values are laid out like the value that replaces them.
+        let patch = parse(r#"[{"op": "replace", "path": "/tags", "value": [1]}]"#).unwrap();
+        let ops = parse_operations(&patch).unwrap();
+        let mut root = parse(INPUT).unwrap();
+        apply_patch(&mut root, &ops, &keys).unwrap();
+        let tags = get_mut(&mut root.value, &["tags".into()]).unwrap();
+        assert!(!tags.expanded());
+    }
+
+    #[test]
+    fn test_apply_patch_sequence() {
+        let keys = Arena::new();
+        let mut root = parse(INPUT).unwrap();
+        for (i, key) in ["/first\"", "/second\""].into_iter().enumerate() {
+            let ops = [Operation::add(
+                Pointer::parse(key).unwrap().into_owned(),
+                Value::new(ValueToken::Number("1"), vec![]),
+            )];
+            apply_patch(&mut root, &ops, &keys).unwrap();
+            assert_eq!(keys.len(), i + 1);
+        }
+        let ValueToken::Object(vals) = &root.value.token else {
+            panic!("expected an object");
+        };
+        let added: Vec<_> = Object::from(vals.clone())
+            .members
+            .iter()
+            .map(|member| member.key)
+            .skip(3)
+            .collect();
+        assert_eq!(added, [r#"first\""#, r#"second\""#]);
+    }
+
+    #[test]
+    fn test_apply_patch_errors() {
+        let cases = [
+            (
+                r#"[{"op": "remove", "path": "/missing"}]"#,
+                Error::Patch(0, "/missing".into(), PatchError::NotFound),
+            ),
+            (
+                r#"[
+                    {"op": "replace", "path": "/name", "value": 1},
+                    {"op": "test", "path": "/name", "value": 2},
+                ]"#,
+                Error::Patch(1, "/name".into(), PatchError::TestFailed),
+            ),
+            (
+                r#"[{"op": "move", "from": "/a~1b", "path": "/a~1b/c/d"}]"#,
+                Error::Patch(0, "/a~1b/c/d".into(), PatchError::Invalid),
+            ),
+            (
+                r#"[{"op": "move", "from": "/missing", "path": "/missing"}]"#,
+                Error::Patch(0, "/missing".into(), PatchError::NotFound),
+            ),
+            (
+                r#"[{"op": "copy", "from": "/tags/2", "path": "/x"}]"#,
+                Error::Patch(0, "/tags/2".into(), PatchError::NotFound),
+            ),
+            (
+                r#"[{"op": "add", "path": "/tags/3", "value": 1}]"#,
+                Error::Patch(0, "/tags/3".into(), PatchError::NotFound),
+            ),
+        ];
+        for (patch, expected) in cases {
+            let patch = parse(patch).unwrap();
+            let ops = parse_operations(&patch).unwrap();
+            let keys = Arena::new();
+            let mut root = parse(INPUT).unwrap();
+            assert_eq!(apply_patch(&mut root, &ops, &keys), Err(expected));
+            // Failed patches leave the document unchanged.
+            assert_eq!(root, parse(INPUT).unwrap());
+        }
+
+        let patch = parse(r#"[{"op": "test", "path": "/a"}, {"op": "frob", "path": "/b"}]"#);
+        assert_eq!(
+            parse_operations(&patch.unwrap()),
+            Err(Error::Patch(0, "/a".into(), PatchError::Invalid))
+        );
+    }
+}
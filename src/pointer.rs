@@ -0,0 +1,181 @@
+//! JSON Pointers (RFC 6901) for addressing values in a JSON document.
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+use crate::{error::Error, escape::unescape};
+
+/// Segment is a single step in the path to a JSON value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// An object key, as its raw (possibly escaped) contents in the source.
+    Key(&'a str),
+    /// An index into an array.
+    Index(usize),
+}
+
+/// Pointer is a JSON Pointer as defined by RFC 6901: a list of reference
+/// tokens that identify a value in a document, e.g. `/a/3/b`. The empty
+/// Pointer identifies the root value.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Pointer<'a> {
+    tokens: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Pointer<'a> {
+    /// Returns the Pointer to the root value.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parses a Pointer from its string representation, such as `/a/3/b`.
+    /// The escape sequences `~0` and `~1` are decoded to `~` and `/`.
+    pub fn parse(s: &'a str) -> Result<Self, Error> {
+        if s.is_empty() {
+            return Ok(Self::root());
+        }
+        if !s.starts_with('/') {
+            return Err(Error::InvalidPointer(0));
+        }
+        let mut tokens = Vec::new();
+        let mut start = 1;
+        for token in s[1..].split('/') {
+            tokens.push(decode_token(token, start)?);
+            start += token.len() + 1;
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Returns the Pointer for a path of `Segment`s.
+    pub fn from_path(path: &[Segment<'a>]) -> Self {
+        let tokens = path
+            .iter()
+            .map(|segment| match segment {
+                Segment::Key(k) => unescape(k),
+                Segment::Index(i) => Cow::Owned(i.to_string()),
+            })
+            .collect();
+        Self { tokens }
+    }
+
+    /// Returns the unescaped reference tokens of the Pointer.
+    pub fn tokens(&self) -> &[Cow<'a, str>] {
+        &self.tokens
+    }
+
+    /// Returns true if the Pointer identifies the root value.
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Appends an unescaped reference token to the Pointer.
+    pub fn push(&mut self, token: impl Into<Cow<'a, str>>) {
+        self.tokens.push(token.into());
+    }
+
+    /// Appends an array index to the Pointer.
+    pub fn push_index(&mut self, index: usize) {
+        self.tokens.push(Cow::Owned(index.to_string()));
+    }
+
+    /// Removes and returns the last reference token of the Pointer.
+    pub fn pop(&mut self) -> Option<Cow<'a, str>> {
+        self.tokens.pop()
+    }
+
+    /// Returns a Pointer that owns all of its reference tokens.
+    pub fn into_owned(self) -> Pointer<'static> {
+        let tokens = self
+            .tokens
+            .into_iter()
+            .map(|token| Cow::Owned(token.into_owned()))
+            .collect();
+        Pointer { tokens }
+    }
+
+    /// Returns true if the Pointer identifies the value at `path`.
+    pub fn matches(&self, path: &[Segment]) -> bool {
+        self.tokens.len() == path.len()
+            && self
+                .tokens
+                .iter()
+                .zip(path)
+                .all(|(token, segment)| match segment {
+                    Segment::Key(k) => unescape(k) == *token,
+                    Segment::Index(i) => parse_index(token) == Some(*i),
+                })
+    }
+}
+
+impl Display for Pointer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            f.write_str("/")?;
+            for c in token.chars() {
+                match c {
+                    '~' => f.write_str("~0")?,
+                    '/' => f.write_str("~1")?,
+                    c => write!(f, "{c}")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the array index for a reference token. Per RFC 6901, indexes are
+/// decimal digits without leading zeros.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn decode_token(token: &str, start: usize) -> Result<Cow<'_, str>, Error> {
+    if !token.contains('~') {
+        return Ok(Cow::Borrowed(token));
+    }
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '~' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '0')) => out.push('~'),
+            Some((_, '1')) => out.push('/'),
+            _ => return Err(Error::InvalidPointer(start + i)),
+        }
+    }
+    Ok(Cow::Owned(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointer() {
+        let pointer = Pointer::parse("/a~1b/0/m~0n/").unwrap();
+        assert_eq!(pointer.tokens(), ["a/b", "0", "m~n", ""]);
+        assert_eq!(pointer.to_string(), "/a~1b/0/m~0n/");
+        assert!(Pointer::parse("").unwrap().is_root());
+        assert_eq!(Pointer::parse("a"), Err(Error::InvalidPointer(0)));
+        assert_eq!(Pointer::parse("/ab/c~2"), Err(Error::InvalidPointer(5)));
+
+        let path = [Segment::Key(r"a/b"), Segment::Index(0)];
+        assert!(Pointer::parse("/a~1b/0").unwrap().matches(&path));
+        assert!(!Pointer::parse("/a~1b/00").unwrap().matches(&path));
+        assert_eq!(Pointer::from_path(&path).to_string(), "/a~1b/0");
+        assert!(Pointer::parse("/A")
+            .unwrap()
+            .matches(&[Segment::Key(r"\u0041")]));
+    }
+}
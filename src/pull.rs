@@ -0,0 +1,260 @@
+//! Pull parser that yields high-level JSONC events without building an AST.
+
+use std::ops::Range;
+
+use crate::{
+    ast::Comment,
+    byte_scanner::ByteScanner,
+    error::Error,
+    pointer::{Pointer, Segment},
+    scanner::{ScanResult, Token},
+    validate::{Validate, ValidateIter},
+};
+
+/// PullEvent is a structural event emitted by the [PullParser].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PullEvent<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// An object key, as its raw contents in the source.
+    Key(&'a str),
+    /// A string, number, boolean, or null value.
+    Value(Scalar<'a>),
+    Comment(Comment<'a>),
+}
+
+/// Scalar represents a JSON value that is not an object or array.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Scalar<'a> {
+    String(&'a str),
+    Number(&'a str),
+    Bool(bool),
+    Null,
+}
+
+/// PullResult represents the output of the PullParser Iterator.
+pub type PullResult<'a> = Result<PullEvent<'a>, Error>;
+
+enum Frame {
+    Object { expect_key: bool, has_key: bool },
+    Array { index: Option<usize> },
+}
+
+/// PullParser validates an `Iterator` of [ScanResult]s and is itself an
+/// `Iterator` over [PullEvent]s. After each event, [PullParser::path] returns
+/// the path to the value that the event belongs to: for `Key` events, the
+/// path includes the key, and for `EndObject`/`EndArray` events it is the
+/// path to the container that ended.
+pub struct PullParser<'a, I: Iterator<Item = ScanResult<'a>>> {
+    iter: Validate<'a, I>,
+    frames: Vec<Frame>,
+    path: Vec<Segment<'a>>,
+    range: Range<usize>,
+}
+
+/// Returns a [PullParser] for the input string.
+pub fn pull(input: &str) -> PullParser<'_, ByteScanner<'_>> {
+    PullParser::new(ByteScanner::new(input))
+}
+
+impl<'a, I> Iterator for PullParser<'a, I>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    type Item = PullResult<'a>;
+
+    fn next(&mut self) -> Option<PullResult<'a>> {
+        loop {
+            let event = match self.iter.next()? {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            let pull = match event.token {
+                Token::ObjectStart => {
+                    self.next_value();
+                    self.frames.push(Frame::Object {
+                        expect_key: true,
+                        has_key: false,
+                    });
+                    PullEvent::StartObject
+                }
+                Token::ArrayStart => {
+                    self.next_value();
+                    self.frames.push(Frame::Array { index: None });
+                    PullEvent::StartArray
+                }
+                Token::ObjectEnd => {
+                    if let Some(Frame::Object { has_key: true, .. }) = self.frames.pop() {
+                        self.path.pop();
+                    }
+                    PullEvent::EndObject
+                }
+                Token::ArrayEnd => {
+                    if let Some(Frame::Array { index: Some(_) }) = self.frames.pop() {
+                        self.path.pop();
+                    }
+                    PullEvent::EndArray
+                }
+                Token::Comma => {
+                    if let Some(Frame::Object { expect_key, .. }) = self.frames.last_mut() {
+                        *expect_key = true;
+                    }
+                    continue;
+                }
+                Token::String(v) => match self.frames.last_mut() {
+                    Some(Frame::Object {
+                        expect_key: expect_key @ true,
+                        has_key,
+                    }) => {
+                        *expect_key = false;
+                        if *has_key {
+                            self.path.pop();
+                        }
+                        *has_key = true;
+                        self.path.push(Segment::Key(v));
+                        PullEvent::Key(v)
+                    }
+                    _ => {
+                        self.next_value();
+                        PullEvent::Value(Scalar::String(v))
+                    }
+                },
+                Token::Number(v) => {
+                    self.next_value();
+                    PullEvent::Value(Scalar::Number(v))
+                }
+                Token::Bool(v) => {
+                    self.next_value();
+                    PullEvent::Value(Scalar::Bool(v))
+                }
+                Token::Null => {
+                    self.next_value();
+                    PullEvent::Value(Scalar::Null)
+                }
+                Token::LineComment(c) => PullEvent::Comment(Comment::Line(c)),
+                Token::BlockComment(c) => PullEvent::Comment(Comment::Block(c)),
+                Token::Colon | Token::Newline | Token::Whitespace(_) => continue,
+            };
+            self.range = event.range;
+            return Some(Ok(pull));
+        }
+    }
+}
+
+impl<'a, I> PullParser<'a, I>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    /// Creates a new PullParser from an `Iterator` of [ScanResult]s, such as a
+    /// `Scanner` or `ByteScanner`.
+    pub fn new(iter: I) -> Self {
+        Self {
+            iter: iter.validate(),
+            frames: Vec::new(),
+            path: Vec::new(),
+            range: 0..0,
+        }
+    }
+
+    /// Returns the path to the current value.
+    pub fn path(&self) -> &[Segment<'a>] {
+        &self.path
+    }
+
+    /// Returns the JSON Pointer to the current value.
+    pub fn pointer(&self) -> Pointer<'a> {
+        Pointer::from_path(&self.path)
+    }
+
+    /// Returns the depth of the current value, where the root value has a
+    /// depth of zero.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Returns the range in the source of the token for the last event.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Advances the index of the enclosing array, if any, for a new value.
+    fn next_value(&mut self) {
+        if let Some(Frame::Array { index }) = self.frames.last_mut() {
+            let next = match index {
+                Some(i) => {
+                    self.path.pop();
+                    *i + 1
+                }
+                None => 0,
+            };
+            *index = Some(next);
+            self.path.push(Segment::Index(next));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull() {
+        let input = r#"{"a": [1, {"b": true}, [], // Comment.
+            "x"], "c": null,}"#;
+        let mut parser = pull(input);
+        let mut output = Vec::new();
+        while let Some(event) = parser.next() {
+            output.push((event.unwrap(), parser.pointer().to_string(), parser.depth()));
+        }
+        let expected = [
+            (PullEvent::StartObject, "", 0),
+            (PullEvent::Key("a"), "/a", 1),
+            (PullEvent::StartArray, "/a", 1),
+            (PullEvent::Value(Scalar::Number("1")), "/a/0", 2),
+            (PullEvent::StartObject, "/a/1", 2),
+            (PullEvent::Key("b"), "/a/1/b", 3),
+            (PullEvent::Value(Scalar::Bool(true)), "/a/1/b", 3),
+            (PullEvent::EndObject, "/a/1", 2),
+            (PullEvent::StartArray, "/a/2", 2),
+            (PullEvent::EndArray, "/a/2", 2),
+            (PullEvent::Comment(Comment::Line(" Comment.")), "/a/2", 2),
+            (PullEvent::Value(Scalar::String("x")), "/a/3", 2),
+            (PullEvent::EndArray, "/a", 1),
+            (PullEvent::Key("c"), "/c", 1),
+            (PullEvent::Value(Scalar::Null), "/c", 1),
+            (PullEvent::EndObject, "", 0),
+        ];
+        let expected = expected
+            .into_iter()
+            .map(|(event, pointer, depth)| (event, pointer.to_string(), depth))
+            .collect::<Vec<_>>();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_pull_extract() {
+        let input = r#"{"items": [{"id": 1}, {"id": 2, "tags": ["x"]}]}"#;
+        let pointer = Pointer::parse("/items/1/id").unwrap();
+        let mut parser = pull(input);
+        let mut found = None;
+        while let Some(event) = parser.next() {
+            if let PullEvent::Value(v) = event.unwrap() {
+                if pointer.matches(parser.path()) {
+                    found = Some((v, parser.range()));
+                }
+            }
+        }
+        assert_eq!(found, Some((Scalar::Number("2"), 29..30)));
+
+        let output = pull("[1, }").collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            output,
+            Err(Error::UnexpectedToken(
+                4..5,
+                crate::error::TokenType::ObjectEnd
+            ))
+        );
+    }
+}
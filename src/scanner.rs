@@ -2,7 +2,11 @@
 
 use std::{iter::Peekable, ops::Range, str::CharIndices};
 
-use crate::error::Error;
+use crate::{
+    error::Error,
+    escape::{parse_unicode_escape, starts_with_low_surrogate},
+    number::Number,
+};
 
 /// Event combines a JSON Token and range in the source string. It is emitted
 /// from the Scanner.
@@ -29,11 +33,75 @@ pub enum Token<'a> {
     String(&'a str),
     Number(&'a str),
     Bool(bool),
+    /// A run of whitespace, other than newlines. Only emitted when enabled
+    /// with `Scanner::with_whitespace`.
+    Whitespace(&'a str),
 }
 
 /// ScanResult represents the output of the Scanner Iterator.
 pub type ScanResult<'a> = Result<Event<'a>, Error>;
 
+/// LineEnding represents the convention used to terminate lines.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Lines end with `\n`.
+    #[default]
+    Lf,
+    /// Lines end with `\r\n`.
+    Crlf,
+}
+
+/// Counts the line endings seen in the source, in order to determine the
+/// dominant convention.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct LineEndings {
+    lf: usize,
+    crlf: usize,
+}
+
+impl LineEndings {
+    pub(crate) fn add_event(&mut self, event: &Event) {
+        match event.token {
+            Token::Newline => self.add_newline(event.range.len() == 2),
+            Token::BlockComment(c) => self.add_text(c),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn add_newline(&mut self, crlf: bool) {
+        if crlf {
+            self.crlf += 1;
+        } else {
+            self.lf += 1;
+        }
+    }
+
+    pub(crate) fn add_text(&mut self, s: &str) {
+        let bytes = s.as_bytes();
+        for (i, _) in s.match_indices('\n') {
+            self.add_newline(i > 0 && bytes[i - 1] == b'\r');
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: LineEndings) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+    }
+
+    pub(crate) fn sub(&mut self, other: LineEndings) {
+        self.lf -= other.lf;
+        self.crlf -= other.crlf;
+    }
+
+    pub(crate) fn dominant(&self) -> LineEnding {
+        if self.crlf > self.lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
 /// Scanner is a lexer for JSON with C-style comments and trailing commas. It is
 /// itself an `Iterator` over `ScanResult`s. Usually, a Scanner is only required
 /// to be used directly when you want to filter out certain Token types (like
@@ -43,6 +111,10 @@ pub struct Scanner<'a> {
     has_error: bool,
     current_idx: usize,
     chars: Peekable<CharIndices<'a>>,
+    line_endings: LineEndings,
+    finite_numbers: bool,
+    reject_lone_surrogates: bool,
+    whitespace: bool,
 }
 
 impl<'a> Iterator for Scanner<'a> {
@@ -71,33 +143,81 @@ impl<'a> Scanner<'a> {
             has_error: false,
             current_idx: 0,
             chars: input.char_indices().peekable(),
+            line_endings: LineEndings::default(),
+            finite_numbers: false,
+            reject_lone_surrogates: false,
+            whitespace: false,
         }
     }
 
-    /// Return an `Iterator` that filters out all C-style comments and newlines.
+    /// Sets whether numbers that cannot be represented as a finite `f64`, such
+    /// as `1e999`, are rejected with an `Error::NumberOutOfRange`. The default
+    /// is false.
+    pub fn with_finite_numbers(self, finite_numbers: bool) -> Self {
+        Self {
+            finite_numbers,
+            ..self
+        }
+    }
+
+    /// Sets whether `\u` escapes of UTF-16 surrogates that are not part of a
+    /// valid pair, such as `"\uD800"`, are rejected with an
+    /// `Error::LoneSurrogate`. These are rejected by strict JSON parsers. The
+    /// default is false.
+    pub fn with_reject_lone_surrogates(self, reject_lone_surrogates: bool) -> Self {
+        Self {
+            reject_lone_surrogates,
+            ..self
+        }
+    }
+
+    /// Sets whether runs of whitespace are emitted as `Token::Whitespace`
+    /// events. When enabled, concatenating the source of every event
+    /// reproduces the input exactly. The default is false.
+    pub fn with_whitespace(self, whitespace: bool) -> Self {
+        Self { whitespace, ..self }
+    }
+
+    /// Returns the line ending convention used most often in the input scanned
+    /// so far, including line breaks inside of block comments. A `\r\n`
+    /// sequence is emitted as a single `Token::Newline` spanning both bytes.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_endings.dominant()
+    }
+
+    /// Return an `Iterator` that filters out all C-style comments, newlines and
+    /// whitespace.
     pub fn without_metadata(self) -> impl Iterator<Item = ScanResult<'a>> {
-        self.into_iter().filter(|event| {
-            if let Ok(event) = event {
-                match event.token {
-                    Token::BlockComment(_) | Token::LineComment(_) | Token::Newline => {
-                        return false
-                    }
-                    _ => {}
-                }
-            }
-            true
-        })
+        filter_metadata(self)
     }
 
     fn parse_value(&mut self) -> Option<ScanResult<'a>> {
-        self.skip_whitespace();
+        if self.whitespace {
+            if let Some(event) = self.parse_whitespace() {
+                return Some(Ok(event));
+            }
+        } else {
+            self.skip_whitespace();
+        }
         if let Some((i, c)) = self.next_char() {
             let start = self.current_idx;
             match c {
-                '\n' => Some(Ok(Event {
-                    token: Token::Newline,
-                    range: start..(start + 1),
-                })),
+                '\n' => {
+                    self.line_endings.add_newline(false);
+                    Some(Ok(Event {
+                        token: Token::Newline,
+                        range: start..(start + 1),
+                    }))
+                }
+                '\r' => {
+                    // Only reached when followed by '\n', see skip_whitespace.
+                    self.skip_char();
+                    self.line_endings.add_newline(true);
+                    Some(Ok(Event {
+                        token: Token::Newline,
+                        range: start..(start + 2),
+                    }))
+                }
                 '{' => Some(Ok(Event {
                     token: Token::ObjectStart,
                     range: start..(start + 1),
@@ -140,6 +260,20 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    fn parse_whitespace(&mut self) -> Option<Event<'a>> {
+        let start = self.peek_char()?.0;
+        self.skip_whitespace();
+        let len = self.input.len();
+        let end = self.peek_char().map_or(len, |&(i, _)| i);
+        if start == end {
+            return None;
+        }
+        Some(Event {
+            token: Token::Whitespace(&self.input[start..end]),
+            range: start..end,
+        })
+    }
+
     fn parse_number(&mut self, start: usize, curr: char) -> ScanResult<'a> {
         let curr = if curr == '-' {
             self.next_digit()?
@@ -166,8 +300,12 @@ impl<'a> Scanner<'a> {
         }
 
         let range = start..(self.current_idx + 1);
+        let number = &self.input[range.clone()];
+        if self.finite_numbers && !Number::new(number).is_finite_f64() {
+            return Err(Error::NumberOutOfRange(range));
+        }
         Ok(Event {
-            token: Token::Number(&self.input[range.clone()]),
+            token: Token::Number(number),
             range,
         })
     }
@@ -176,21 +314,24 @@ impl<'a> Scanner<'a> {
         while let Some((i, c)) = self.next_char() {
             match c {
                 '\\' => match self.next_char() {
-                    Some((i, c)) => match c {
+                    Some((j, c)) => match c {
                         '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => {}
                         'u' => {
                             for _ in 0..4 {
                                 match self.next_char() {
-                                    Some((i, c)) => {
+                                    Some((j, c)) => {
                                         if !c.is_ascii_hexdigit() {
-                                            return Err(Error::UnexpectedCharacter(i, c));
+                                            return Err(Error::UnexpectedCharacter(j, c));
                                         }
                                     }
                                     None => return Err(Error::UnexpectedEOF),
                                 }
                             }
+                            if self.reject_lone_surrogates {
+                                self.check_surrogate(i)?;
+                            }
                         }
-                        c => return Err(Error::UnexpectedCharacter(i, c)),
+                        c => return Err(Error::UnexpectedCharacter(j, c)),
                     },
                     None => return Err(Error::UnexpectedEOF),
                 },
@@ -202,7 +343,7 @@ impl<'a> Scanner<'a> {
                     });
                 }
                 c => {
-                    if !(0x0020..0x10FFFF).contains(&(c as u32)) {
+                    if (c as u32) < 0x0020 {
                         return Err(Error::UnexpectedCharacter(i, c));
                     }
                 }
@@ -211,6 +352,22 @@ impl<'a> Scanner<'a> {
         Err(Error::UnexpectedEOF)
     }
 
+    /// Checks the `\uXXXX` escape starting at index `start`, which has already
+    /// been consumed, skipping over the low half of a valid surrogate pair.
+    fn check_surrogate(&mut self, start: usize) -> Result<(), Error> {
+        let bytes = self.input.as_bytes();
+        match parse_unicode_escape(&bytes[start..]) {
+            Some(0xD800..=0xDBFF) if starts_with_low_surrogate(&bytes[(start + 6)..]) => {
+                for _ in 0..6 {
+                    self.skip_char();
+                }
+                Ok(())
+            }
+            Some(0xD800..=0xDFFF) => Err(Error::LoneSurrogate(start..(start + 6))),
+            _ => Ok(()),
+        }
+    }
+
     fn parse_comment(&mut self, start: usize) -> ScanResult<'a> {
         match self.next_char() {
             Some((_, '/')) => self.parse_line_comment(start),
@@ -221,30 +378,13 @@ impl<'a> Scanner<'a> {
     }
 
     fn parse_line_comment(&mut self, start: usize) -> ScanResult<'a> {
-        let mut end;
-        loop {
-            match self.peek_char() {
-                Some(&(i, c)) => {
-                    end = i;
-                    if c == '\n' {
-                        break;
-                    } else if c == '\r' {
-                        self.skip_char();
-                        if let Some(&(_, c)) = self.peek_char() {
-                            if c == '\n' {
-                                break;
-                            }
-                        }
-                        continue;
-                    } else {
-                        self.skip_char();
-                    }
-                }
-                None => {
-                    end = self.input.len();
-                    break;
-                }
+        let mut end = self.input.len();
+        while let Some(&(i, c)) = self.peek_char() {
+            if c == '\n' || self.is_crlf(i) {
+                end = i;
+                break;
             }
+            self.skip_char();
         }
         Ok(Event {
             token: Token::LineComment(&self.input[start + 2..end]),
@@ -257,8 +397,10 @@ impl<'a> Scanner<'a> {
             if c == '*' {
                 if let Some(&(i, '/')) = self.peek_char() {
                     self.skip_char();
+                    let comment = &self.input[(start + 2)..(i - 1)];
+                    self.line_endings.add_text(comment);
                     return Ok(Event {
-                        token: Token::BlockComment(&self.input[(start + 2)..(i - 1)]),
+                        token: Token::BlockComment(comment),
                         range: start..(i + 1),
                     });
                 }
@@ -324,8 +466,8 @@ impl<'a> Scanner<'a> {
     }
 
     fn skip_whitespace(&mut self) {
-        while let Some(c) = self.peek_char() {
-            if c.1.is_whitespace() && c.1 != '\n' {
+        while let Some(&(i, c)) = self.peek_char() {
+            if c.is_whitespace() && c != '\n' && !self.is_crlf(i) {
                 self.skip_char();
             } else {
                 return;
@@ -333,6 +475,10 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    fn is_crlf(&self, i: usize) -> bool {
+        self.input.as_bytes()[i..].starts_with(b"\r\n")
+    }
+
     fn next_chars_equal(&mut self, s: &str) -> bool {
         for ch in s.chars() {
             match self.next_char() {
@@ -367,6 +513,24 @@ impl<'a> Scanner<'a> {
     }
 }
 
+pub(crate) fn filter_metadata<'a, I>(iter: I) -> impl Iterator<Item = ScanResult<'a>>
+where
+    I: Iterator<Item = ScanResult<'a>>,
+{
+    iter.filter(|event| {
+        if let Ok(event) = event {
+            match event.token {
+                Token::BlockComment(_)
+                | Token::LineComment(_)
+                | Token::Newline
+                | Token::Whitespace(_) => return false,
+                _ => {}
+            }
+        }
+        true
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -538,10 +702,56 @@ mod tests {
                 Token::String(v) => assert_eq!(&input[event.range], ["\"", v, "\""].join("")),
                 Token::Number(v) => assert_eq!(&input[event.range], v),
                 Token::Bool(v) => assert_eq!(&input[event.range], if v { "true" } else { "false" }),
+                Token::Whitespace(v) => assert_eq!(&input[event.range], v),
             }
         }
     }
 
+    #[test]
+    fn test_whitespace() {
+        let input = " {\t\"a\" :\u{a0}[1 ,2],\r\n  \r// c\n}  ";
+        let scanner = Scanner::new(input).with_whitespace(true);
+        let output = scanner.map(|v| v.unwrap()).collect::<Vec<_>>();
+        assert_eq!(
+            output[..3],
+            [
+                Event {
+                    token: Token::Whitespace(" "),
+                    range: 0..1,
+                },
+                Event {
+                    token: Token::ObjectStart,
+                    range: 1..2,
+                },
+                Event {
+                    token: Token::Whitespace("\t"),
+                    range: 2..3,
+                },
+            ]
+        );
+        let mut end = 0;
+        for event in &output {
+            assert_eq!(event.range.start, end);
+            end = event.range.end;
+        }
+        assert_eq!(end, input.len());
+
+        let expected = Scanner::new(input).collect::<Vec<_>>();
+        let output = Scanner::new(input)
+            .with_whitespace(true)
+            .filter(|v| {
+                !matches!(
+                    v,
+                    Ok(Event {
+                        token: Token::Whitespace(_),
+                        ..
+                    })
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(output, expected);
+    }
+
     #[test]
     fn test_line_comment() {
         let input = "//";
@@ -554,6 +764,68 @@ mod tests {
         assert_eq!(output, vec![exp]);
     }
 
+    #[test]
+    fn test_crlf() {
+        let input = "[ // Comment.\r\n\r\r\n/*\r\n*/1\r]\n";
+        let expected = vec![
+            Event {
+                token: Token::ArrayStart,
+                range: 0..1,
+            },
+            Event {
+                token: Token::LineComment(" Comment."),
+                range: 2..13,
+            },
+            Event {
+                token: Token::Newline,
+                range: 13..15,
+            },
+            Event {
+                token: Token::Newline,
+                range: 16..18,
+            },
+            Event {
+                token: Token::BlockComment("\r\n"),
+                range: 18..24,
+            },
+            Event {
+                token: Token::Number("1"),
+                range: 24..25,
+            },
+            Event {
+                token: Token::ArrayEnd,
+                range: 26..27,
+            },
+            Event {
+                token: Token::Newline,
+                range: 27..28,
+            },
+        ];
+        let mut scanner = Scanner::new(input);
+        let output = scanner.by_ref().map(|v| v.unwrap()).collect::<Vec<_>>();
+        assert_eq!(output, expected);
+        assert_eq!(scanner.line_ending(), LineEnding::Crlf);
+    }
+
+    #[test]
+    fn test_string() {
+        let input = concat!(
+            "[\"\u{10FFFF}\", ",
+            r#""\uD83D\uDE00", "\uDE00", "\uD83D\n"]"#
+        );
+        let output = Scanner::new(input).collect::<Result<Vec<_>, _>>();
+        assert!(output.is_ok());
+
+        let scanner = Scanner::new(input).with_reject_lone_surrogates(true);
+        let output = scanner.collect::<Result<Vec<_>, _>>();
+        assert_eq!(output, Err(Error::LoneSurrogate(26..32)));
+
+        let input = r#""\uD83D\uDE00 \uD83D\n""#;
+        let scanner = Scanner::new(input).with_reject_lone_surrogates(true);
+        let output = scanner.collect::<Result<Vec<_>, _>>();
+        assert_eq!(output, Err(Error::LoneSurrogate(14..20)));
+    }
+
     #[test]
     fn test_number() {
         let input = "0.01";
@@ -564,5 +836,12 @@ mod tests {
         let scanner = Scanner::new(input);
         let output = scanner.map(|v| v.unwrap()).collect::<Vec<_>>();
         assert_eq!(output, vec![exp]);
+
+        let input = "[1e308, -1e309]";
+        let output = Scanner::new(input).collect::<Result<Vec<_>, _>>();
+        assert!(output.is_ok());
+        let scanner = Scanner::new(input).with_finite_numbers(true);
+        let output = scanner.collect::<Result<Vec<_>, _>>();
+        assert_eq!(output, Err(Error::NumberOutOfRange(8..14)));
     }
 }
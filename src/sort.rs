@@ -0,0 +1,187 @@
+//! Sort object keys while keeping comments with their members.
+
+use std::cmp::Ordering;
+
+use crate::{
+    ast::{ObjectValue, Root},
+    attached::{Member, Object},
+    escape::unescape,
+    visit::{walk_object_mut, Path, VisitorMut},
+};
+
+/// KeyOrder determines the order that object keys are sorted in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyOrder<'a> {
+    /// Keys are sorted by their unescaped characters.
+    Alphabetical,
+    /// Keys are sorted alphabetically, except that runs of digits are
+    /// compared by their numeric value, e.g. `item2` before `item10`.
+    Natural,
+    /// Keys found in the list are sorted first, in the order of the list.
+    /// All other keys follow in their original order.
+    Custom(&'a [&'a str]),
+}
+
+impl KeyOrder<'_> {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (unescape(a), unescape(b));
+        match self {
+            KeyOrder::Alphabetical => a.cmp(&b),
+            KeyOrder::Natural => natural_cmp(&a, &b).then_with(|| a.cmp(&b)),
+            KeyOrder::Custom(keys) => {
+                let position = |k: &str| keys.iter().position(|v| *v == k).unwrap_or(keys.len());
+                position(&a).cmp(&position(&b))
+            }
+        }
+    }
+}
+
+/// Sorts the keys of every object in the `Root`.
+///
+/// Each member is moved along with the comments directly above it and its
+/// same-line comments. A blank line between members starts a new group, and
+/// members are only sorted within their group; any comments above a group stay
+/// at the start of the group. The sort is stable, so duplicate keys keep their
+/// order.
+pub fn sort_keys(root: &mut Root, order: KeyOrder) {
+    SortKeys { order }.visit_root_mut(root);
+}
+
+struct SortKeys<'o> {
+    order: KeyOrder<'o>,
+}
+
+impl<'a> VisitorMut<'a> for SortKeys<'_> {
+    fn visit_object_mut(&mut self, path: &mut Path<'a>, vals: &mut Vec<ObjectValue<'a>>) {
+        walk_object_mut(self, path, vals);
+        let mut object = Object::from(std::mem::take(vals));
+        let mut start = 0;
+        for i in 1..=object.members.len() {
+            if i == object.members.len() || object.members[i].starts_group() {
+                self.sort_group(&mut object.members[start..i]);
+                start = i;
+            }
+        }
+        *vals = object.into();
+    }
+}
+
+impl SortKeys<'_> {
+    fn sort_group(&self, group: &mut [Member]) {
+        let Some(first) = group.first_mut() else {
+            return;
+        };
+        let header = std::mem::take(&mut first.detached);
+        group.sort_by(|a, b| self.order.compare(a.key, b.key));
+        group[0].detached = header;
+    }
+}
+
+/// Compares strings, treating runs of ASCII digits as numbers.
+fn natural_cmp(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        if a.is_empty() || b.is_empty() {
+            return a.len().cmp(&b.len());
+        }
+        let (chunk_a, rest_a) = split_chunk(a);
+        let (chunk_b, rest_b) = split_chunk(b);
+        let is_number = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+        let ord = if is_number(chunk_a) && is_number(chunk_b) {
+            let (num_a, num_b) = (
+                chunk_a.trim_start_matches('0'),
+                chunk_b.trim_start_matches('0'),
+            );
+            num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b))
+        } else {
+            chunk_a.cmp(chunk_b)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+        (a, b) = (rest_a, rest_b);
+    }
+}
+
+/// Splits off the leading run of digits or non-digits.
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digit)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse;
+
+    #[test]
+    fn test_sort_keys() {
+        let input = r#"{
+            // Group one.
+
+            // Documents c.
+            "c": 1, // Same line c.
+            "a": {"z": [{"y": 1, "x": 2}], "b": null},
+
+            // Group two.
+
+            "item10": 1,
+            "item2": 2,
+            // Dangling.
+        }"#;
+
+        let mut root = parse(input).unwrap();
+        sort_keys(&mut root, KeyOrder::Alphabetical);
+        let expected = parse(
+            r#"{
+            // Group one.
+
+            "a": {"b": null, "z": [{"x": 2, "y": 1}]},
+            // Documents c.
+            "c": 1, // Same line c.
+
+            // Group two.
+
+            "item10": 1,
+            "item2": 2,
+            // Dangling.
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+
+        let mut root = parse(input).unwrap();
+        sort_keys(&mut root, KeyOrder::Natural);
+        let expected = parse(
+            r#"{
+            // Group one.
+
+            "a": {"b": null, "z": [{"x": 2, "y": 1}]},
+            // Documents c.
+            "c": 1, // Same line c.
+
+            // Group two.
+
+            "item2": 2,
+            "item10": 1,
+            // Dangling.
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+    }
+
+    #[test]
+    fn test_sort_keys_custom() {
+        let mut root = parse(r#"{"version": 1, "z": 2, "name": 3, "b": 4}"#).unwrap();
+        sort_keys(&mut root, KeyOrder::Custom(&["name", "version"]));
+        let expected = parse(r#"{"name": 3, "version": 1, "z": 2, "b": 4}"#).unwrap();
+        assert_eq!(root, expected);
+
+        assert_eq!(natural_cmp("a1b", "a01b"), Ordering::Equal);
+        assert_eq!(natural_cmp("a9", "a10"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+    }
+}
@@ -1,6 +1,6 @@
 //! Validate JSON(C) without creating an AST.
 
-use std::iter::Peekable;
+use std::{collections::VecDeque, iter::Peekable};
 
 use crate::{
     scanner::{Event, ScanResult, Token},
@@ -48,10 +48,15 @@ enum ObjectState {
 
 /// Validate an `Iterator` of [ScanResult]s without building an AST
 /// [crate::ast::Root] struct.
+///
+/// Every event from the wrapped iterator is emitted in its original order,
+/// including comment, newline and whitespace events, such as a comment
+/// between a value and the `,` or `:` that follows it.
 pub struct Validate<'a, I: Iterator<Item = ScanResult<'a>>> {
     iter: Peekable<I>,
     has_error: bool,
     stack: ArrayVec<State, MAX_RECURSION>,
+    pending: VecDeque<Event<'a>>,
 }
 
 impl<'a, I> Iterator for Validate<'a, I>
@@ -85,6 +90,7 @@ where
             iter: iter.peekable(),
             has_error: false,
             stack: ArrayVec::new(),
+            pending: VecDeque::new(),
         }
     }
 
@@ -234,6 +240,8 @@ where
         }
     }
 
+    /// Peeks at the next event that isn't a comment, newline or whitespace.
+    /// Any of those skipped over are kept to be emitted next.
     fn peek_next(&mut self) -> Result<Option<Event<'a>>, Error> {
         loop {
             let event = match self.iter.peek() {
@@ -245,15 +253,23 @@ where
             };
             if !matches!(
                 event.token,
-                Token::LineComment(_) | Token::BlockComment(_) | Token::Newline
+                Token::LineComment(_)
+                    | Token::BlockComment(_)
+                    | Token::Newline
+                    | Token::Whitespace(_)
             ) {
                 return Ok(Some(event.clone()));
             }
-            self.next_event()?;
+            if let Some(Ok(event)) = self.iter.next() {
+                self.pending.push_back(event);
+            }
         }
     }
 
     fn next_event(&mut self) -> Result<Option<Event<'a>>, Error> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(Some(event));
+        }
         match self.iter.next() {
             Some(Ok(event)) => Ok(Some(event)),
             Some(Err(err)) => Err(err),
@@ -298,6 +314,29 @@ mod tests {
         assert_eq!(out, expected);
     }
 
+    #[test]
+    fn test_validate_metadata() {
+        let input = "{\"a\" /* A. */ : [1 // One.\n, 2]}";
+        let expected = vec![
+            Token::ObjectStart,
+            Token::String("a"),
+            Token::BlockComment(" A. "),
+            Token::Colon,
+            Token::ArrayStart,
+            Token::Number("1"),
+            Token::LineComment(" One."),
+            Token::Newline,
+            Token::Comma,
+            Token::Number("2"),
+            Token::ArrayEnd,
+            Token::ObjectEnd,
+        ];
+
+        let iter = Validate::new(Scanner::new(input));
+        let out = iter.map(|v| v.unwrap().token).collect::<Vec<_>>();
+        assert_eq!(out, expected);
+    }
+
     #[test]
     fn test_validate_fail() {
         let input = r#"{"key":true"#;
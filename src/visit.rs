@@ -0,0 +1,345 @@
+//! Traverse an AST with the [Visitor] and [VisitorMut] traits.
+//!
+//! Every method has a default implementation that calls the matching `walk_*`
+//! function to visit the node's children, so an implementation only needs to
+//! override the methods for the nodes it cares about. An overriding method can
+//! call the `walk_*` function itself to continue into the children.
+
+use crate::{
+    ast::{ArrayValue, Comment, Metadata, ObjectValue, Root, Value, ValueToken},
+    pointer::{Pointer, Segment},
+};
+
+/// Path is the location of the node being visited. Object keys and the
+/// comments of a value are visited with the path of their value, while
+/// metadata inside of a container is visited with the path of the container.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path<'a> {
+    segments: Vec<Segment<'a>>,
+}
+
+impl<'a> Path<'a> {
+    /// Returns the segments of the path.
+    pub fn segments(&self) -> &[Segment<'a>] {
+        &self.segments
+    }
+
+    /// Returns the JSON Pointer for the path.
+    pub fn pointer(&self) -> Pointer<'a> {
+        Pointer::from_path(&self.segments)
+    }
+
+    /// Returns the number of containers enclosing the current node.
+    pub fn depth(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Appends a segment to the path.
+    pub fn push(&mut self, segment: Segment<'a>) {
+        self.segments.push(segment);
+    }
+
+    /// Removes the last segment of the path.
+    pub fn pop(&mut self) -> Option<Segment<'a>> {
+        self.segments.pop()
+    }
+}
+
+/// Visitor traverses a [Root] by reference.
+pub trait Visitor<'a> {
+    fn visit_root(&mut self, root: &Root<'a>) {
+        walk_root(self, root);
+    }
+
+    fn visit_value(&mut self, path: &mut Path<'a>, value: &Value<'a>) {
+        walk_value(self, path, value);
+    }
+
+    fn visit_object(&mut self, path: &mut Path<'a>, vals: &[ObjectValue<'a>]) {
+        walk_object(self, path, vals);
+    }
+
+    fn visit_array(&mut self, path: &mut Path<'a>, vals: &[ArrayValue<'a>]) {
+        walk_array(self, path, vals);
+    }
+
+    fn visit_key(&mut self, _path: &mut Path<'a>, _key: &'a str) {}
+
+    /// Visits a string, number, bool, or null value.
+    fn visit_scalar(&mut self, _path: &mut Path<'a>, _token: &ValueToken<'a>) {}
+
+    fn visit_metadata(&mut self, path: &mut Path<'a>, meta: &Metadata<'a>) {
+        walk_metadata(self, path, meta);
+    }
+
+    fn visit_comment(&mut self, _path: &mut Path<'a>, _comment: &Comment<'a>) {}
+
+    fn visit_newline(&mut self, _path: &mut Path<'a>) {}
+}
+
+pub fn walk_root<'a, V: Visitor<'a> + ?Sized>(v: &mut V, root: &Root<'a>) {
+    let mut path = Path::default();
+    for meta in &root.meta_above {
+        v.visit_metadata(&mut path, meta);
+    }
+    v.visit_value(&mut path, &root.value);
+    for meta in &root.meta_below {
+        v.visit_metadata(&mut path, meta);
+    }
+}
+
+pub fn walk_value<'a, V: Visitor<'a> + ?Sized>(v: &mut V, path: &mut Path<'a>, value: &Value<'a>) {
+    match &value.token {
+        ValueToken::Object(vals) => v.visit_object(path, vals),
+        ValueToken::Array(vals) => v.visit_array(path, vals),
+        token => v.visit_scalar(path, token),
+    }
+    for comment in &value.comments {
+        v.visit_comment(path, comment);
+    }
+}
+
+pub fn walk_object<'a, V: Visitor<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    vals: &[ObjectValue<'a>],
+) {
+    for val in vals {
+        match val {
+            ObjectValue::Metadata(meta) => v.visit_metadata(path, meta),
+            ObjectValue::KeyVal(key, value) => {
+                path.push(Segment::Key(key));
+                v.visit_key(path, key);
+                v.visit_value(path, value);
+                path.pop();
+            }
+        }
+    }
+}
+
+pub fn walk_array<'a, V: Visitor<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    vals: &[ArrayValue<'a>],
+) {
+    let mut index = 0;
+    for val in vals {
+        match val {
+            ArrayValue::Metadata(meta) => v.visit_metadata(path, meta),
+            ArrayValue::ArrayVal(value) => {
+                path.push(Segment::Index(index));
+                v.visit_value(path, value);
+                path.pop();
+                index += 1;
+            }
+        }
+    }
+}
+
+pub fn walk_metadata<'a, V: Visitor<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    meta: &Metadata<'a>,
+) {
+    match meta {
+        Metadata::Comment(comment) => v.visit_comment(path, comment),
+        Metadata::Newline => v.visit_newline(path),
+    }
+}
+
+/// VisitorMut traverses a [Root] by mutable reference. Object and array
+/// members are provided as `Vec`s so that they can be added or removed.
+pub trait VisitorMut<'a> {
+    fn visit_root_mut(&mut self, root: &mut Root<'a>) {
+        walk_root_mut(self, root);
+    }
+
+    fn visit_value_mut(&mut self, path: &mut Path<'a>, value: &mut Value<'a>) {
+        walk_value_mut(self, path, value);
+    }
+
+    fn visit_object_mut(&mut self, path: &mut Path<'a>, vals: &mut Vec<ObjectValue<'a>>) {
+        walk_object_mut(self, path, vals);
+    }
+
+    fn visit_array_mut(&mut self, path: &mut Path<'a>, vals: &mut Vec<ArrayValue<'a>>) {
+        walk_array_mut(self, path, vals);
+    }
+
+    fn visit_key_mut(&mut self, _path: &mut Path<'a>, _key: &mut &'a str) {}
+
+    /// Visits a string, number, bool, or null value.
+    fn visit_scalar_mut(&mut self, _path: &mut Path<'a>, _token: &mut ValueToken<'a>) {}
+
+    fn visit_metadata_mut(&mut self, path: &mut Path<'a>, meta: &mut Metadata<'a>) {
+        walk_metadata_mut(self, path, meta);
+    }
+
+    fn visit_comment_mut(&mut self, _path: &mut Path<'a>, _comment: &mut Comment<'a>) {}
+
+    fn visit_newline_mut(&mut self, _path: &mut Path<'a>) {}
+}
+
+pub fn walk_root_mut<'a, V: VisitorMut<'a> + ?Sized>(v: &mut V, root: &mut Root<'a>) {
+    let mut path = Path::default();
+    for meta in &mut root.meta_above {
+        v.visit_metadata_mut(&mut path, meta);
+    }
+    v.visit_value_mut(&mut path, &mut root.value);
+    for meta in &mut root.meta_below {
+        v.visit_metadata_mut(&mut path, meta);
+    }
+}
+
+pub fn walk_value_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    value: &mut Value<'a>,
+) {
+    match &mut value.token {
+        ValueToken::Object(vals) => v.visit_object_mut(path, vals),
+        ValueToken::Array(vals) => v.visit_array_mut(path, vals),
+        token => v.visit_scalar_mut(path, token),
+    }
+    for comment in &mut value.comments {
+        v.visit_comment_mut(path, comment);
+    }
+}
+
+pub fn walk_object_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    vals: &mut Vec<ObjectValue<'a>>,
+) {
+    for val in vals {
+        match val {
+            ObjectValue::Metadata(meta) => v.visit_metadata_mut(path, meta),
+            ObjectValue::KeyVal(key, value) => {
+                path.push(Segment::Key(key));
+                v.visit_key_mut(path, key);
+                v.visit_value_mut(path, value);
+                path.pop();
+            }
+        }
+    }
+}
+
+pub fn walk_array_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    vals: &mut Vec<ArrayValue<'a>>,
+) {
+    let mut index = 0;
+    for val in vals {
+        match val {
+            ArrayValue::Metadata(meta) => v.visit_metadata_mut(path, meta),
+            ArrayValue::ArrayVal(value) => {
+                path.push(Segment::Index(index));
+                v.visit_value_mut(path, value);
+                path.pop();
+                index += 1;
+            }
+        }
+    }
+}
+
+pub fn walk_metadata_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    v: &mut V,
+    path: &mut Path<'a>,
+    meta: &mut Metadata<'a>,
+) {
+    match meta {
+        Metadata::Comment(comment) => v.visit_comment_mut(path, comment),
+        Metadata::Newline => v.visit_newline_mut(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::parse;
+
+    const INPUT: &str = r#"// Top.
+    {
+        "name": "fjson", // Name.
+
+        "auth": {"user": "me", "password": "secret"},
+        "list": [1, /* One. */ {"password": "x"}],
+    }"#;
+
+    #[test]
+    fn test_visitor() {
+        #[derive(Default)]
+        struct Collect {
+            keys: Vec<String>,
+            comments: Vec<String>,
+            newlines: usize,
+        }
+
+        impl<'a> Visitor<'a> for Collect {
+            fn visit_key(&mut self, path: &mut Path<'a>, _key: &'a str) {
+                self.keys.push(path.pointer().to_string());
+            }
+
+            fn visit_comment(&mut self, path: &mut Path<'a>, comment: &Comment<'a>) {
+                let (Comment::Line(c) | Comment::Block(c)) = comment;
+                self.comments.push(format!("{}:{c}", path.pointer()));
+            }
+
+            fn visit_newline(&mut self, _path: &mut Path<'a>) {
+                self.newlines += 1;
+            }
+        }
+
+        let root = parse(INPUT).unwrap();
+        let mut collect = Collect::default();
+        collect.visit_root(&root);
+        assert_eq!(
+            collect.keys,
+            [
+                "/name",
+                "/auth",
+                "/auth/user",
+                "/auth/password",
+                "/list",
+                "/list/1/password"
+            ]
+        );
+        assert_eq!(
+            collect.comments,
+            [": Top.", "/name: Name.", "/list/0: One. "]
+        );
+        assert_eq!(collect.newlines, 1);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        struct Redact;
+
+        impl<'a> VisitorMut<'a> for Redact {
+            fn visit_object_mut(&mut self, path: &mut Path<'a>, vals: &mut Vec<ObjectValue<'a>>) {
+                vals.retain(|val| !matches!(val, ObjectValue::Metadata(_)));
+                walk_object_mut(self, path, vals);
+            }
+
+            fn visit_scalar_mut(&mut self, path: &mut Path<'a>, token: &mut ValueToken<'a>) {
+                if let Some(Segment::Key("password")) = path.segments().last() {
+                    *token = ValueToken::String("REDACTED");
+                }
+            }
+        }
+
+        let mut root = parse(INPUT).unwrap();
+        Redact.visit_root_mut(&mut root);
+        let expected = parse(
+            r#"// Top.
+            {
+                "name": "fjson", // Name.
+                "auth": {"user": "me", "password": "REDACTED"},
+                "list": [1, /* One. */ {"password": "REDACTED"}],
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(root, expected);
+    }
+}